@@ -17,9 +17,16 @@ cargo build --release --target wasm32-unknown-unknown -p rpg-game
 echo "Build complete!"
 echo "Contract WASM: target/wasm32-unknown-unknown/release/rpg_game_contract.wasm"
 echo "Service WASM: target/wasm32-unknown-unknown/release/rpg_game_service.wasm"
+echo "Hub contract WASM: target/wasm32-unknown-unknown/release/rpg_hub_contract.wasm"
+echo "Hub service WASM: target/wasm32-unknown-unknown/release/rpg_hub_service.wasm"
 
 echo ""
 echo "To deploy the application to Linera:"
 echo "linera publish-and-create \\"
 echo "  target/wasm32-unknown-unknown/release/rpg_game_contract.wasm \\"
-echo "  target/wasm32-unknown-unknown/release/rpg_game_service.wasm"
\ No newline at end of file
+echo "  target/wasm32-unknown-unknown/release/rpg_game_service.wasm"
+echo ""
+echo "To deploy the achievement hub to Linera:"
+echo "linera publish-and-create \\"
+echo "  target/wasm32-unknown-unknown/release/rpg_hub_contract.wasm \\"
+echo "  target/wasm32-unknown-unknown/release/rpg_hub_service.wasm"
@@ -3,16 +3,21 @@
 
 #![cfg_attr(target_arch = "wasm32", no_main)]
 
-mod state;
-
-use rpg_game::{RpgGameAbi, RpgGameOperation, RpgGameMessage};
+use rpg_game::{
+    progression, HubOperation, InventoryData, InventoryItem, ItemDefinition, PlayerState, QuestData, RpgGameAbi, RpgGameError,
+    RpgGameOperation, RpgGameMessage, RpgGameResponse, RpgHubAbi, SaveInvariant, SaveRules, Shop, SlotKind, StatModifiers,
+    TradeItem, TradeStatus, TransferStatus, TransferTicket,
+};
 use linera_sdk::{
-    linera_base_types::WithContractAbi,
+    linera_base_types::{AccountOwner, ApplicationId, ChainId, WithContractAbi},
     views::{RootView, View},
     Contract, ContractRuntime,
 };
-use serde_json;
-use state::{InventoryData, PlayerData, RpgGameState, BattleRecord, GuildData, PlayerTransferRequest};
+use rpg_game::state::{
+    BattleRecord, CurrencyTransfer, GuildData, LedgerEntry, LedgerEntryKind, PlayerData, PlayerTransferRequest, RpgGameState,
+    TradeOffer,
+};
+use serde_json::{self, json};
 
 pub struct RpgGameContract {
     state: RpgGameState,
@@ -42,267 +47,464 @@ impl Contract for RpgGameContract {
         // Validate that the application parameters were configured correctly.
         let params = self.runtime.application_parameters();
         if params.world_region != world_region {
-            println!("World region parameter mismatch");
+            log::warn!("World region parameter mismatch");
         }
 
         // Set the world region for this chain
         self.state.world_region.set(world_region);
+
+        // The listed admins, plus whoever created the application
+        for owner in params.admins.iter().copied().chain(self.runtime.authenticated_signer()) {
+            self.state.admins.insert(&owner).expect("Failed to add admin");
+        }
     }
 
-    async fn execute_operation(&mut self, operation: RpgGameOperation) -> () {
-        match operation {
-            RpgGameOperation::SavePlayerState {
+    async fn execute_operation(&mut self, operation: RpgGameOperation) -> Result<RpgGameResponse, RpgGameError> {
+        // A refused operation keeps none of its writes; operations only send messages once nothing can fail
+        let response = self.execute(operation).await;
+        if let Err(error) = &response {
+            log::warn!("Operation refused: {}", error);
+            self.state.rollback();
+        }
+        response
+    }
+
+    async fn execute_message(&mut self, message: RpgGameMessage) {
+        match message {
+            RpgGameMessage::PlayerTransfer {
                 player_id,
-                health,
-                max_health,
-                strength,
-                wisdomness,
-                benchpress,
-                curl,
-                experience,
-                level,
+                player_state,
+                inventory,
+                quests,
+                nonce,
+                auth_token,
+                balance,
             } => {
-                let player_data = PlayerData {
-                    health,
-                    max_health,
-                    strength,
-                    wisdomness,
-                    benchpress,
-                    curl,
-                    experience,
-                    level,
+                // A bounced transfer never reached the destination, so the player stays on this chain
+                if self.runtime.message_is_bouncing() == Some(true) {
+                    self.fail_transfer(&player_id, nonce, RpgGameError::TransferBounced).await;
+                    return;
+                }
+
+                let Some(source_chain) = self.runtime.message_origin_chain_id() else {
+                    log::warn!("Rejected transfer of player {}: message has no origin chain", player_id);
+                    return;
+                };
+
+                // The player keeps the owner that signed the transfer on the source chain
+                let Some(owner) = self.runtime.authenticated_signer() else {
+                    self.reject_transfer(source_chain, player_id, nonce, RpgGameError::MissingSigner);
+                    return;
+                };
+
+                // The token must match the authenticated signer and origin of this message and its payload
+                let ticket = TransferTicket {
+                    player_id: player_id.clone(),
+                    owner,
+                    source_chain,
+                    destination_chain: self.runtime.chain_id(),
+                    nonce,
+                    player_state: player_state.clone(),
+                    inventory: inventory.clone(),
+                    quests: quests.clone(),
+                    balance,
                 };
+                let consumed = self.state.consumed_transfer_tokens.contains_key(&auth_token).await
+                    .expect("Failed to get consumed transfer tokens");
+                if let Err(error) = ticket.check(&auth_token, consumed) {
+                    self.reject_transfer(source_chain, player_id, nonce, error);
+                    return;
+                }
 
-                if let Err(e) = self.state.player_states.insert(&player_id, player_data) {
-                    println!("Failed to save player state to blockchain for player {}: {}", player_id, e);
+                // A player that left this chain earlier may come back under a new owner
+                let has_left = self.state.player_transfer_requests.get(&player_id).await
+                    .expect("Failed to get player transfer request")
+                    .is_some_and(|request| request.status == TransferStatus::Completed);
+                let existing_owner = self.state.player_owners.get(&player_id).await
+                    .expect("Failed to get player owner");
+                if !has_left && existing_owner.is_some_and(|existing| existing != owner) {
+                    self.reject_transfer(source_chain, player_id.clone(), nonce, RpgGameError::Unauthorized(player_id));
+                    return;
                 }
-            }
-            RpgGameOperation::SaveInventory { player_id, inventory } => {
-                // Parse the inventory JSON string into the proper structure
-                let inventory_data: Vec<crate::state::InventoryItem> =
+
+                let player_exists = self.state.player_states.contains_key(&player_id).await
+                    .expect("Failed to get player state");
+                if !has_left && player_exists {
+                    self.reject_transfer(source_chain, player_id.clone(), nonce, RpgGameError::PlayerAlreadyExists(player_id));
+                    return;
+                }
+
+                // Validate the whole payload before writing anything
+                let inventory_data: Vec<InventoryItem> =
                     match serde_json::from_str(&inventory) {
                         Ok(data) => data,
                         Err(e) => {
-                            println!("Failed to parse inventory JSON for player {}: {}", player_id, e);
-                            return; // Exit the operation early if JSON parsing fails
+                            self.reject_transfer(source_chain, player_id, nonce, RpgGameError::MalformedInventory(e.to_string()));
+                            return;
                         }
                     };
-
                 let inventory_struct = InventoryData {
                     items: inventory_data,
                 };
-
-                if let Err(e) = self.state.player_inventories.insert(&player_id, inventory_struct) {
-                    println!("Failed to save inventory to blockchain for player {}: {}", player_id, e);
+                // The player may only bring items this chain's catalogue knows
+                if let Err(error) = self.check_catalogue(&inventory_struct).await {
+                    self.reject_transfer(source_chain, player_id, nonce, error);
+                    return;
                 }
-            }
-            RpgGameOperation::SaveQuests { player_id, quests } => {
-                // Parse the quests JSON string into the proper structure
-                let quests_data: Vec<crate::state::QuestData> =
+
+                let quests_data: Vec<QuestData> =
                     match serde_json::from_str(&quests) {
                         Ok(data) => data,
                         Err(e) => {
-                            println!("Failed to parse quests JSON for player {}: {}", player_id, e);
-                            return; // Exit the operation early if JSON parsing fails
+                            self.reject_transfer(source_chain, player_id, nonce, RpgGameError::MalformedQuests(e.to_string()));
+                            return;
                         }
                     };
 
-                if let Err(e) = self.state.player_quests.insert(&player_id, quests_data) {
-                    println!("Failed to save quests to blockchain for player {}: {}", player_id, e);
+                // A player that left this chain earlier may still have a balance here
+                if let Err(error) = self.check_credit(&player_id, balance).await {
+                    self.reject_transfer(source_chain, player_id, nonce, error);
+                    return;
                 }
-            }
-            RpgGameOperation::TransferPlayer {
-                player_id,
-                destination_chain,
-                player_state,
-                inventory,
-                quests,
-                auth_token,
-            } => {
-                // Transfer player to another chain by sending a cross-chain message
-                let transfer_message = RpgGameMessage::PlayerTransfer {
-                    player_id: player_id.clone(),
-                    player_state,
-                    inventory,
-                    quests,
-                    auth_token: auth_token.clone(),
+
+                // Convert PlayerState to PlayerData
+                let player_data = PlayerData {
+                    health: player_state.health,
+                    max_health: player_state.max_health,
+                    strength: player_state.strength,
+                    wisdomness: player_state.wisdomness,
+                    benchpress: player_state.benchpress,
+                    curl: player_state.curl,
+                    experience: player_state.experience,
+                    level: player_state.level,
                 };
 
-                // Send the player data to the destination chain
-                self.runtime
-                    .prepare_message(transfer_message)
-                    .with_authentication()
-                    .send_to(destination_chain);
+                // Storage failures from here on panic, so the tracked message bounces back to the source chain
+                let timestamp = self.runtime.system_time().micros();
+                self.state.consumed_transfer_tokens.insert(&auth_token, timestamp)
+                    .expect("Failed to record transfer token");
+                self.state.player_owners.insert(&player_id, owner)
+                    .expect("Failed to save transferred player owner");
+                self.state.player_states.insert(&player_id, player_data)
+                    .expect("Failed to save transferred player state");
+                self.state.player_inventories.insert(&player_id, inventory_struct)
+                    .expect("Failed to save transferred inventory");
+                self.state.player_quests.insert(&player_id, quests_data)
+                    .expect("Failed to save transferred quests");
+                if balance > 0 {
+                    let arrival = LedgerEntry {
+                        counterparty_chain: Some(source_chain),
+                        ..LedgerEntry::new(LedgerEntryKind::ChainArrival, balance, auth_token.clone())
+                    };
+                    self.post_entry(&player_id, arrival).await
+                        .expect("Failed to credit transferred balance");
+                }
 
-                // Remove the player from the current chain (deleting the old data)
-                // We don't delete immediately because the transfer might fail and need to be retried
-                let transfer_request = PlayerTransferRequest {
-                    source_chain: self.runtime.chain_id(),
-                    destination_chain,
+                // The player has arrived, so any record of it leaving this chain earlier is obsolete
+                self.state.player_transfer_requests.remove(&player_id)
+                    .expect("Failed to remove player transfer request");
+
+                // Let the source chain release its copy of the player
+                let acknowledgement = RpgGameMessage::PlayerTransferAcknowledged {
                     player_id: player_id.clone(),
-                    auth_token,
-                    timestamp: self.runtime.system_time().micros(),
+                    nonce,
                 };
+                self.runtime
+                    .prepare_message(acknowledgement)
+                    .send_to(source_chain);
 
-                if let Err(e) = self.state.player_transfer_requests.insert(&player_id, transfer_request) {
-                    println!("Failed to save player transfer request for player {}: {}", player_id, e);
-                }
+                log::info!("Player {} successfully transferred to this chain", player_id);
             }
-            RpgGameOperation::JoinGuild {
-                player_id,
-                guild_id,
-                chain_id,
-            } => {
-                // Send a cross-chain message to join a guild on another chain
-                let join_message = RpgGameMessage::GuildJoinRequest {
-                    player_id: player_id.clone(),
-                    guild_id: guild_id.clone(),
+            RpgGameMessage::PlayerTransferAcknowledged { player_id, nonce } => {
+                let origin = self.runtime.message_origin_chain_id();
+                let Some(request) = self.state.player_transfer_requests.get_mut(&player_id).await.expect("Failed to get player transfer request") else {
+                    log::warn!("No pending transfer for player {}", player_id);
+                    return;
                 };
 
-                self.runtime
-                    .prepare_message(join_message)
-                    .with_authentication()
-                    .send_to(chain_id);
-
-                // Add to join request list temporarily
-                if let Some(mut requests) = self.state.guild_join_requests.get_mut(&guild_id).await.expect("Failed to get guild join requests") {
-                    requests.push(player_id);
-                } else {
-                    let mut requests = Vec::new();
-                    requests.push(player_id);
-                    if let Err(e) = self.state.guild_join_requests.insert(&guild_id, requests) {
-                        println!("Failed to save guild join request for guild {}: {}", guild_id, e);
-                    }
+                // Only the chain the transfer was sent to can complete it
+                if request.nonce != nonce || request.status != TransferStatus::Pending || origin != Some(request.destination_chain) {
+                    log::warn!("Ignored transfer acknowledgement for player {}: no matching transfer", player_id);
+                    return;
                 }
+                request.status = TransferStatus::Completed;
+
+                // The player now lives on the destination chain; the completed request keeps it locked here
+                self.state.player_states.remove(&player_id)
+                    .expect("Failed to remove player state");
+                self.state.player_inventories.remove(&player_id)
+                    .expect("Failed to remove player inventory");
+                self.state.player_quests.remove(&player_id)
+                    .expect("Failed to remove player quests");
+
+                log::info!("Player {} left this chain", player_id);
             }
-            RpgGameOperation::RecordBattle {
-                battle_id,
+            RpgGameMessage::PlayerTransferRejected {
                 player_id,
-                opponent,
-                player_result,
-                damage_dealt,
-                damage_taken,
-                experience_gained,
+                nonce,
+                error,
             } => {
-                // Create a battle record
-                let battle_record = BattleRecord {
-                    battle_id: battle_id.clone(),
-                    player_id: player_id.clone(),
-                    opponent,
-                    result: player_result,
-                    damage_dealt,
-                    damage_taken,
-                    experience_gained,
-                    timestamp: self.runtime.system_time().micros(),
+                // Only the chain the transfer was sent to can reject it
+                let origin = self.runtime.message_origin_chain_id();
+                let destination = self.state.player_transfer_requests.get(&player_id).await
+                    .expect("Failed to get player transfer request")
+                    .map(|request| request.destination_chain);
+                if origin.is_none() || origin != destination {
+                    log::warn!("Ignored transfer rejection for player {}: no matching transfer", player_id);
+                    return;
+                }
+
+                self.fail_transfer(&player_id, nonce, error).await;
+            }
+            RpgGameMessage::CurrencyTransfer {
+                transfer_id,
+                sender_id,
+                recipient_id,
+                amount,
+            } => {
+                // The recipient's chain refused the currency; the sender gets it back
+                if self.runtime.message_is_bouncing() == Some(true) {
+                    self.fail_currency_transfer(&transfer_id, RpgGameError::TransferBounced).await;
+                    return;
+                }
+
+                let Some(source_chain) = self.runtime.message_origin_chain_id() else {
+                    log::warn!("Rejected currency transfer {}: message has no origin chain", transfer_id);
+                    return;
                 };
 
-                // Save the battle record
-                if let Err(e) = self.state.battle_records.insert(&battle_id, battle_record) {
-                    println!("Failed to save battle record for battle {}: {}", battle_id, e);
+                if let Err(error) = self.check_payee(&recipient_id, amount).await {
+                    log::warn!("Rejected currency transfer {}: {}", transfer_id, error);
+                    let rejection = RpgGameMessage::CurrencyTransferRejected { transfer_id, error };
+                    self.runtime
+                        .prepare_message(rejection)
+                        .send_to(source_chain);
+                    return;
                 }
 
-                // Add to player's battle history
-                if let Some(mut battles) = self.state.player_battles.get_mut(&player_id).await.expect("Failed to get player battles") {
-                    battles.push(battle_id.clone());
-                } else {
-                    let mut battles = Vec::new();
-                    battles.push(battle_id.clone());
-                    if let Err(e) = self.state.player_battles.insert(&player_id, battles) {
-                        println!("Failed to save player battle history for player {}: {}", player_id, e);
-                    }
+                let receipt = LedgerEntry {
+                    counterparty: Some(sender_id),
+                    counterparty_chain: Some(source_chain),
+                    ..LedgerEntry::new(LedgerEntryKind::TransferIn, amount, transfer_id.clone())
+                };
+                self.post_entry(&recipient_id, receipt).await
+                    .expect("Failed to credit currency transfer");
+
+                let acknowledgement = RpgGameMessage::CurrencyTransferAcknowledged { transfer_id };
+                self.runtime
+                    .prepare_message(acknowledgement)
+                    .send_to(source_chain);
+            }
+            RpgGameMessage::CurrencyTransferAcknowledged { transfer_id } => {
+                // Only the chain the currency was sent to can complete the transfer
+                let origin = self.runtime.message_origin_chain_id();
+                let Some(mut transfer) = self.state.currency_transfers.get(&transfer_id).await.expect("Failed to get currency transfer") else {
+                    log::warn!("No pending currency transfer {}", transfer_id);
+                    return;
+                };
+                if transfer.status != TransferStatus::Pending || origin != Some(transfer.recipient_chain) {
+                    log::warn!("Ignored acknowledgement of currency transfer {}: no matching transfer", transfer_id);
+                    return;
                 }
+
+                transfer.status = TransferStatus::Completed;
+                self.state.currency_transfers.insert(&transfer_id, transfer)
+                    .expect("Failed to save currency transfer");
             }
-        }
-    }
+            RpgGameMessage::CurrencyTransferRejected { transfer_id, error } => {
+                // Only the chain the currency was sent to can reject the transfer
+                let origin = self.runtime.message_origin_chain_id();
+                let recipient_chain = self.state.currency_transfers.get(&transfer_id).await
+                    .expect("Failed to get currency transfer")
+                    .map(|transfer| transfer.recipient_chain);
+                if origin.is_none() || origin != recipient_chain {
+                    log::warn!("Ignored rejection of currency transfer {}: no matching transfer", transfer_id);
+                    return;
+                }
 
-    async fn execute_message(&mut self, message: RpgGameMessage) {
-        match message {
-            RpgGameMessage::PlayerTransfer {
-                player_id,
-                player_state,
-                inventory,
-                quests,
-                auth_token,
+                self.fail_currency_transfer(&transfer_id, error).await;
+            }
+            RpgGameMessage::TradeProposed {
+                trade_id,
+                proposer_id,
+                counterparty_id,
+                offered,
+                requested,
             } => {
-                // Verify auth token if needed, then add the player to this chain
-                // For now, we'll accept all transfers (in a real implementation, we'd verify the auth token)
+                // The counterparty never saw a bounced proposal, so the proposer gets the escrow back
+                if self.runtime.message_is_bouncing() == Some(true) {
+                    self.release_escrow(&trade_id, TradeStatus::Failed, Some(RpgGameError::TradeBounced.to_string())).await;
+                    return;
+                }
 
-                // Convert PlayerState to PlayerData
-                let player_data = PlayerData {
-                    health: player_state.health,
-                    max_health: player_state.max_health,
-                    strength: player_state.strength,
-                    wisdomness: player_state.wisdomness,
-                    benchpress: player_state.benchpress,
-                    curl: player_state.curl,
-                    experience: player_state.experience,
-                    level: player_state.level,
+                let Some(proposer_chain) = self.runtime.message_origin_chain_id() else {
+                    log::warn!("Rejected trade {}: message has no origin chain", trade_id);
+                    return;
                 };
 
-                // Save the player state
-                if let Err(e) = self.state.player_states.insert(&player_id, player_data) {
-                    println!("Failed to save transferred player state for player {}: {}", player_id, e);
+                // Trade IDs are issued by the proposer's chain
+                let known = self.state.trades.contains_key(&trade_id).await.expect("Failed to get trade");
+                if known || !trade_id.starts_with(&format!("{}-", proposer_chain)) {
+                    log::warn!("Ignored trade {}: unexpected trade ID", trade_id);
                     return;
                 }
 
-                // Save the inventory
-                let inventory_data: Vec<crate::state::InventoryItem> =
-                    match serde_json::from_str(&inventory) {
-                        Ok(data) => data,
-                        Err(e) => {
-                            println!("Failed to parse transferred inventory JSON for player {}: {}", player_id, e);
-                            return;
-                        }
+                if let Err(error) = self.check_incoming_trade(&counterparty_id, &offered, &requested).await {
+                    log::warn!("Rejected trade {}: {}", trade_id, error);
+                    let closure = RpgGameMessage::TradeClosed {
+                        trade_id,
+                        status: TradeStatus::Failed,
+                        failure_reason: Some(error.to_string()),
                     };
+                    self.runtime
+                        .prepare_message(closure)
+                        .send_to(proposer_chain);
+                    return;
+                }
 
-                let inventory_struct = InventoryData {
-                    items: inventory_data,
+                let counterparty_chain = self.runtime.chain_id();
+                let created_at = self.runtime.system_time().micros();
+                self.save_trade(TradeOffer {
+                    trade_id,
+                    proposer_id,
+                    proposer_chain,
+                    counterparty_id,
+                    counterparty_chain,
+                    offered,
+                    requested,
+                    escrow: Vec::new(),
+                    status: TradeStatus::Pending,
+                    created_at,
+                    failure_reason: None,
+                }).await;
+            }
+            RpgGameMessage::TradeCancelRequested { trade_id } => {
+                // Only the proposer's chain can cancel a trade that is still pending
+                let origin = self.runtime.message_origin_chain_id();
+                let Some(trade) = self.state.trades.get(&trade_id).await.expect("Failed to get trade") else {
+                    log::warn!("No trade {} to cancel", trade_id);
+                    return;
                 };
+                if origin != Some(trade.proposer_chain) || trade.status != TradeStatus::Pending {
+                    log::warn!("Ignored cancellation of trade {}: no matching pending trade", trade_id);
+                    return;
+                }
 
-                if let Err(e) = self.state.player_inventories.insert(&player_id, inventory_struct) {
-                    println!("Failed to save transferred inventory for player {}: {}", player_id, e);
+                if let Err(error) = self.close_trade(trade, TradeStatus::Cancelled).await {
+                    log::warn!("Failed to cancel trade {}: {}", trade_id, error);
+                }
+            }
+            RpgGameMessage::TradeClosed {
+                trade_id,
+                status,
+                failure_reason,
+            } => {
+                // Only the counterparty's chain can close the trade
+                let origin = self.runtime.message_origin_chain_id();
+                let counterparty_chain = self.state.trades.get(&trade_id).await
+                    .expect("Failed to get trade")
+                    .map(|trade| trade.counterparty_chain);
+                if origin.is_none() || origin != counterparty_chain {
+                    log::warn!("Ignored closure of trade {}: no matching trade", trade_id);
                     return;
                 }
 
-                // Save the quests
-                let quests_data: Vec<crate::state::QuestData> =
-                    match serde_json::from_str(&quests) {
-                        Ok(data) => data,
-                        Err(e) => {
-                            println!("Failed to parse transferred quests JSON for player {}: {}", player_id, e);
-                            return;
-                        }
+                self.release_escrow(&trade_id, status, failure_reason).await;
+            }
+            RpgGameMessage::TradeAccepted { trade_id, items } => {
+                let bouncing = self.runtime.message_is_bouncing() == Some(true);
+                let origin = self.runtime.message_origin_chain_id();
+                let Some(mut trade) = self.state.trades.get(&trade_id).await.expect("Failed to get trade") else {
+                    log::warn!("No trade {} to settle", trade_id);
+                    return;
+                };
+
+                // The proposer's chain could not settle, so the counterparty takes the requested items back
+                if bouncing {
+                    if trade.status != TradeStatus::Accepted {
+                        log::warn!("Ignored bounced acceptance of trade {}: no matching accepted trade", trade_id);
+                        return;
+                    }
+                    let failure_reason = Some(RpgGameError::TradeBounced.to_string());
+                    trade.status = TradeStatus::Failed;
+                    trade.failure_reason = failure_reason.clone();
+                    trade.escrow = self.deliver_trade_items(&trade.counterparty_id, items).await;
+
+                    let closure = RpgGameMessage::TradeClosed {
+                        trade_id: trade_id.clone(),
+                        status: TradeStatus::Failed,
+                        failure_reason,
                     };
+                    self.runtime
+                        .prepare_message(closure)
+                        .send_to(trade.proposer_chain);
+                    self.state.trades.insert(&trade_id, trade)
+                        .expect("Failed to save trade");
+                    return;
+                }
+
+                if origin != Some(trade.counterparty_chain) || trade.status != TradeStatus::Pending {
+                    log::warn!("Ignored acceptance of trade {}: no matching pending trade", trade_id);
+                    return;
+                }
+
+                // Swap: the proposer receives the requested items and the escrow goes to the counterparty
+                trade.status = TradeStatus::Completed;
+                let offered = std::mem::take(&mut trade.escrow);
+                trade.escrow = self.deliver_trade_items(&trade.proposer_id, items).await;
+
+                let settlement = RpgGameMessage::TradeSettled {
+                    trade_id: trade_id.clone(),
+                    items: offered,
+                };
+                self.runtime
+                    .prepare_message(settlement)
+                    .send_to(trade.counterparty_chain);
+                self.state.trades.insert(&trade_id, trade)
+                    .expect("Failed to save trade");
 
-                if let Err(e) = self.state.player_quests.insert(&player_id, quests_data) {
-                    println!("Failed to save transferred quests for player {}: {}", player_id, e);
+                log::info!("Trade {} completed", trade_id);
+            }
+            RpgGameMessage::TradeSettled { trade_id, items } => {
+                // Only the proposer's chain can settle an accepted trade
+                let origin = self.runtime.message_origin_chain_id();
+                let Some(mut trade) = self.state.trades.get(&trade_id).await.expect("Failed to get trade") else {
+                    log::warn!("No trade {} to settle", trade_id);
+                    return;
+                };
+                if origin != Some(trade.proposer_chain) || trade.status != TradeStatus::Accepted {
+                    log::warn!("Ignored settlement of trade {}: no matching accepted trade", trade_id);
                     return;
                 }
 
-                println!("Player {} successfully transferred to this chain", player_id);
+                trade.status = TradeStatus::Completed;
+                trade.escrow = self.deliver_trade_items(&trade.counterparty_id, items).await;
+                self.state.trades.insert(&trade_id, trade)
+                    .expect("Failed to save trade");
+
+                log::info!("Trade {} completed", trade_id);
             }
             RpgGameMessage::GuildJoinRequest {
                 player_id,
                 guild_id,
             } => {
                 // Add the player to the guild if it exists
-                if let Some(mut guild) = self.state.guilds.get_mut(&guild_id).await.expect("Failed to get guild") {
+                if let Some(guild) = self.state.guilds.get_mut(&guild_id).await.expect("Failed to get guild") {
                     // Check if player is already in the guild
                     if !guild.members.contains(&player_id) {
                         guild.members.push(player_id.clone());
 
                         // Also update the player's guild mapping
-                        if let Err(e) = self.state.player_guilds.insert(&player_id, guild_id.clone()) {
-                            println!("Failed to update player guild mapping for player {}: {}", player_id, e);
-                        }
+                        self.state.player_guilds.insert(&player_id, guild_id.clone())
+                            .expect("Failed to update player guild mapping");
 
-                        println!("Player {} joined guild {} on this chain", player_id, guild_id);
+                        log::info!("Player {} joined guild {} on this chain", player_id, guild_id);
                     } else {
-                        println!("Player {} is already in guild {}", player_id, guild_id);
+                        log::info!("Player {} is already in guild {}", player_id, guild_id);
                     }
                 } else {
                     // Guild doesn't exist on this chain, create a new one with the player
-                    let mut new_guild = GuildData {
+                    let new_guild = GuildData {
                         id: guild_id.clone(),
                         name: format!("Guild_{}", guild_id),  // In a real impl, we'd fetch the name from the source
                         members: vec![player_id.clone()],
@@ -310,16 +512,11 @@ impl Contract for RpgGameContract {
                         level: 1,
                     };
 
-                    if let Err(e) = self.state.guilds.insert(&guild_id, new_guild) {
-                        println!("Failed to create new guild {}: {}", guild_id, e);
-                        return;
-                    }
-
-                    if let Err(e) = self.state.player_guilds.insert(&player_id, guild_id.clone()) {
-                        println!("Failed to update player guild mapping for player {}: {}", player_id, e);
-                    }
+                    self.state.guilds.insert(&guild_id, new_guild).expect("Failed to create guild");
+                    self.state.player_guilds.insert(&player_id, guild_id.clone())
+                        .expect("Failed to update player guild mapping");
 
-                    println!("Player {} joined new guild {} on this chain", player_id, guild_id);
+                    log::info!("Player {} joined new guild {} on this chain", player_id, guild_id);
                 }
             }
             RpgGameMessage::BattleResult {
@@ -331,7 +528,13 @@ impl Contract for RpgGameContract {
                 damage_taken,
                 experience_gained,
             } => {
+                if self.state.battle_records.contains_key(&battle_id).await.expect("Failed to get battle record") {
+                    log::warn!("Ignored result of battle {}: it is already recorded", battle_id);
+                    return;
+                }
+
                 // Record the battle result
+                let player_stats = self.state.effective_stats(&player_id).await;
                 let battle_record = BattleRecord {
                     battle_id: battle_id.clone(),
                     player_id: player_id.clone(),
@@ -341,24 +544,20 @@ impl Contract for RpgGameContract {
                     damage_taken,
                     experience_gained,
                     timestamp: self.runtime.system_time().micros(),
+                    player_stats,
                 };
 
-                if let Err(e) = self.state.battle_records.insert(&battle_id, battle_record) {
-                    println!("Failed to save battle result for battle {}: {}", battle_id, e);
-                    return;
-                }
+                self.state.battle_records.insert(&battle_id, battle_record)
+                    .expect("Failed to save battle result");
 
-                if let Some(mut battles) = self.state.player_battles.get_mut(&player_id).await.expect("Failed to get player battles") {
+                if let Some(battles) = self.state.player_battles.get_mut(&player_id).await.expect("Failed to get player battles") {
                     battles.push(battle_id.clone());
                 } else {
-                    let mut battles = Vec::new();
-                    battles.push(battle_id.clone());
-                    if let Err(e) = self.state.player_battles.insert(&player_id, battles) {
-                        println!("Failed to save player battle history for player {}: {}", player_id, e);
-                    }
+                    self.state.player_battles.insert(&player_id, vec![battle_id.clone()])
+                        .expect("Failed to save player battle history");
                 }
 
-                println!("Battle {} result recorded for player {}", battle_id, player_id);
+                log::info!("Battle {} result recorded for player {}", battle_id, player_id);
             }
         }
     }
@@ -366,4 +565,1287 @@ impl Contract for RpgGameContract {
     async fn store(mut self) {
         self.state.save().await.expect("Failed to save state");
     }
-}
\ No newline at end of file
+}
+
+impl RpgGameContract {
+    /// Executes `operation`, or returns why it is refused.
+    async fn execute(&mut self, operation: RpgGameOperation) -> Result<RpgGameResponse, RpgGameError> {
+        match operation {
+            RpgGameOperation::RegisterPlayer { player_id } => {
+                let signer = self.runtime.authenticated_signer()
+                    .ok_or(RpgGameError::MissingSigner)?;
+
+                if self.state.player_owners.contains_key(&player_id).await.expect("Failed to get player owner") {
+                    return Err(RpgGameError::PlayerAlreadyRegistered(player_id));
+                }
+
+                self.state.player_owners.insert(&player_id, signer)
+                    .expect("Failed to register player");
+                Ok(RpgGameResponse::Ok)
+            }
+            RpgGameOperation::TransferPlayerOwnership { player_id, new_owner } => {
+                self.check_player_access(&player_id).await?;
+
+                self.state.player_owners.insert(&player_id, new_owner)
+                    .expect("Failed to transfer player ownership");
+                Ok(RpgGameResponse::Ok)
+            }
+            RpgGameOperation::SavePlayerState {
+                player_id,
+                health,
+                max_health,
+                strength,
+                wisdomness,
+                benchpress,
+                curl,
+                experience,
+                level,
+            } => {
+                self.check_player_access(&player_id).await?;
+
+                let player_data = PlayerData {
+                    health,
+                    max_health,
+                    strength,
+                    wisdomness,
+                    benchpress,
+                    curl,
+                    experience,
+                    level,
+                };
+
+                let recorded = self.state.player_states.get(&player_id).await
+                    .expect("Failed to get player state");
+                let rules = self.runtime.application_parameters().save_rules;
+                let equipment = self.state.equipment_modifiers(&player_id).await;
+                Self::check_save_rules(&rules, recorded.as_ref(), &player_data, &equipment)?;
+
+                self.state.player_states.insert(&player_id, player_data)
+                    .expect("Failed to save player state");
+                Ok(RpgGameResponse::Ok)
+            }
+            RpgGameOperation::AddExperience { player_id, amount } => {
+                self.check_admin().await?;
+                self.check_trader(&player_id).await?;
+
+                let mut player_data = self.state.player_states.get(&player_id).await
+                    .expect("Failed to get player state")
+                    .ok_or_else(|| RpgGameError::MissingPlayerState(player_id.clone()))?;
+                let levels_gained = player_data.add_experience(amount);
+                let level = player_data.level;
+
+                self.state.player_states.insert(&player_id, player_data)
+                    .expect("Failed to save player state");
+
+                let previous_level = level - levels_gained;
+                for milestone in progression::LEVEL_MILESTONES {
+                    if previous_level < milestone && milestone <= level {
+                        self.report_achievement(&player_id, format!("level_{}", milestone), json!({ "level": milestone }));
+                    }
+                }
+                if levels_gained > 0 {
+                    self.report_progress(&player_id, level, 0);
+                }
+                Ok(RpgGameResponse::ExperienceAdded { level, levels_gained })
+            }
+            RpgGameOperation::CreditCurrency { player_id, amount, reason } => {
+                self.check_admin().await?;
+                self.check_trader(&player_id).await?;
+                if amount == 0 {
+                    return Err(RpgGameError::InvalidAmount);
+                }
+
+                let balance = self.post_entry(&player_id, LedgerEntry::new(LedgerEntryKind::Credit, amount, reason)).await?;
+                Ok(RpgGameResponse::Balance { balance })
+            }
+            RpgGameOperation::DebitCurrency { player_id, amount, reason } => {
+                self.check_admin().await?;
+                self.check_trader(&player_id).await?;
+                if amount == 0 {
+                    return Err(RpgGameError::InvalidAmount);
+                }
+
+                let balance = self.post_entry(&player_id, LedgerEntry::new(LedgerEntryKind::Debit, amount, reason)).await?;
+                Ok(RpgGameResponse::Balance { balance })
+            }
+            RpgGameOperation::TransferCurrency {
+                player_id,
+                recipient_id,
+                recipient_chain,
+                amount,
+            } => {
+                self.check_player_access(&player_id).await?;
+                if amount == 0 {
+                    return Err(RpgGameError::InvalidAmount);
+                }
+
+                let chain_id = self.runtime.chain_id();
+                if recipient_chain == chain_id {
+                    if recipient_id == player_id {
+                        return Err(RpgGameError::InvalidCurrencyTransfer("players cannot pay themselves".to_string()));
+                    }
+                    // Check the credit first, so the debit is never posted alone
+                    self.check_payee(&recipient_id, amount).await?;
+
+                    let payment = LedgerEntry {
+                        counterparty: Some(recipient_id.clone()),
+                        ..LedgerEntry::new(LedgerEntryKind::TransferOut, amount, String::new())
+                    };
+                    let balance = self.post_entry(&player_id, payment).await?;
+                    let receipt = LedgerEntry {
+                        counterparty: Some(player_id),
+                        ..LedgerEntry::new(LedgerEntryKind::TransferIn, amount, String::new())
+                    };
+                    self.post_entry(&recipient_id, receipt).await?;
+                    return Ok(RpgGameResponse::Balance { balance });
+                }
+
+                // The sender is debited now and refunded if the destination chain refuses the transfer
+                let nonce = *self.state.next_currency_transfer_nonce.get();
+                let transfer_id = format!("{}-{}", chain_id, nonce);
+                let payment = LedgerEntry {
+                    counterparty: Some(recipient_id.clone()),
+                    counterparty_chain: Some(recipient_chain),
+                    ..LedgerEntry::new(LedgerEntryKind::TransferOut, amount, transfer_id.clone())
+                };
+                let balance = self.post_entry(&player_id, payment).await?;
+                self.state.next_currency_transfer_nonce.set(nonce + 1);
+
+                let transfer_message = RpgGameMessage::CurrencyTransfer {
+                    transfer_id: transfer_id.clone(),
+                    sender_id: player_id.clone(),
+                    recipient_id: recipient_id.clone(),
+                    amount,
+                };
+                self.runtime
+                    .prepare_message(transfer_message)
+                    .with_authentication()
+                    .with_tracking()
+                    .send_to(recipient_chain);
+
+                let transfer = CurrencyTransfer {
+                    transfer_id: transfer_id.clone(),
+                    sender_id: player_id,
+                    recipient_id,
+                    recipient_chain,
+                    amount,
+                    timestamp: self.runtime.system_time().micros(),
+                    status: TransferStatus::Pending,
+                    failure_reason: None,
+                };
+                self.state.currency_transfers.insert(&transfer_id, transfer)
+                    .expect("Failed to save currency transfer");
+                Ok(RpgGameResponse::CurrencyTransferStarted { transfer_id, balance })
+            }
+            RpgGameOperation::DefineShop { shop } => {
+                self.check_admin().await?;
+                shop.validate()?;
+                for listing in &shop.listings {
+                    if !self.state.item_catalogue.contains_key(&listing.item_id).await.expect("Failed to get item definition") {
+                        return Err(RpgGameError::UnknownItem(listing.item_id.clone()));
+                    }
+                }
+
+                let shop_id = shop.id.clone();
+                let shop = Shop::new(shop, self.runtime.system_time().micros());
+                self.state.shops.insert(&shop_id, shop)
+                    .expect("Failed to save shop");
+                Ok(RpgGameResponse::Ok)
+            }
+            RpgGameOperation::BuyItem { player_id, shop_id, item_id, quantity } => {
+                self.check_player_access(&player_id).await?;
+                if quantity == 0 {
+                    return Err(RpgGameError::InvalidAmount);
+                }
+
+                let mut shop = self.shop(&shop_id).await?;
+                shop.restock(self.runtime.system_time().micros());
+                let Some(item) = shop.item_mut(&item_id) else {
+                    return Err(RpgGameError::NotInShop { shop_id, item_id });
+                };
+                if item.stock < quantity {
+                    return Err(RpgGameError::OutOfStock { shop_id, item_id, stock: item.stock });
+                }
+                item.stock -= quantity;
+                let price = item.listing.price;
+
+                // The bought units form one stack, which must fit the catalogue's stack size
+                let params = self.item_definition(&item_id).await?.params;
+                let mut inventory = self.inventory(&player_id).await;
+                let slot = inventory.add_item(item_id, quantity, params, None)?;
+                self.check_catalogue(&inventory).await?;
+
+                // Paying is the last check, so nothing is written if the player cannot afford the items
+                let Some(cost) = price.checked_mul(quantity) else {
+                    return Err(RpgGameError::InsufficientFunds {
+                        player_id: player_id.clone(),
+                        balance: self.balance(&player_id).await,
+                        amount: u64::MAX,
+                    });
+                };
+                let balance = self.post_entry(&player_id, LedgerEntry::new(LedgerEntryKind::Purchase, cost, shop_id.clone())).await?;
+
+                self.state.player_inventories.insert(&player_id, inventory)
+                    .expect("Failed to save inventory");
+                self.state.shops.insert(&shop_id, shop)
+                    .expect("Failed to save shop");
+                Ok(RpgGameResponse::ItemBought { slot, balance })
+            }
+            RpgGameOperation::SellItem { player_id, shop_id, slot, quantity } => {
+                self.check_player_access(&player_id).await?;
+                if quantity == 0 {
+                    return Err(RpgGameError::InvalidAmount);
+                }
+
+                let mut shop = self.shop(&shop_id).await?;
+                shop.restock(self.runtime.system_time().micros());
+                let mut inventory = self.inventory(&player_id).await;
+                let sold = inventory.take_from_slot(&slot, quantity)?;
+
+                let not_in_shop = || RpgGameError::NotInShop {
+                    shop_id: shop_id.clone(),
+                    item_id: sold.item_id.clone(),
+                };
+                let item = shop.item_mut(&sold.item_id).ok_or_else(not_in_shop)?;
+                let buyback_price = item.listing.buyback_price.ok_or_else(not_in_shop)?;
+                // The shop puts the units back on sale, up to its maximum stock
+                item.stock = item.stock.saturating_add(quantity).min(item.listing.max_stock);
+
+                let payout = buyback_price.checked_mul(quantity)
+                    .ok_or_else(|| RpgGameError::BalanceOverflow(player_id.clone()))?;
+                let balance = self.post_entry(&player_id, LedgerEntry::new(LedgerEntryKind::Sale, payout, shop_id.clone())).await?;
+
+                self.state.player_inventories.insert(&player_id, inventory)
+                    .expect("Failed to save inventory");
+                self.state.shops.insert(&shop_id, shop)
+                    .expect("Failed to save shop");
+                Ok(RpgGameResponse::Balance { balance })
+            }
+            RpgGameOperation::SaveInventory { player_id, items } => {
+                self.check_player_access(&player_id).await?;
+
+                let inventory = self.inventory(&player_id).await.rearrange(items)?;
+                self.check_catalogue(&inventory).await?;
+                self.state.player_inventories.insert(&player_id, inventory)
+                    .expect("Failed to save inventory");
+                Ok(RpgGameResponse::Ok)
+            }
+            RpgGameOperation::DefineQuest { definition } => {
+                self.check_admin().await?;
+
+                let id = definition.id.clone();
+                self.state.quest_catalogue.insert(&id, definition)
+                    .expect("Failed to save quest definition");
+                Ok(RpgGameResponse::Ok)
+            }
+            RpgGameOperation::CompleteQuest { player_id, quest_id } => {
+                self.check_admin().await?;
+                self.check_trader(&player_id).await?;
+
+                let definition = self.state.quest_catalogue.get(&quest_id).await
+                    .expect("Failed to get quest definition")
+                    .ok_or_else(|| RpgGameError::UnknownQuest(quest_id.clone()))?;
+                let key = (player_id.clone(), quest_id.clone());
+                if self.state.completed_quests.contains(&key).await.expect("Failed to get completed quests") {
+                    return Err(RpgGameError::QuestAlreadyCompleted { player_id, quest_id });
+                }
+                self.state.completed_quests.insert(&key)
+                    .expect("Failed to save completed quest");
+
+                let mut quests = self.state.player_quests.get(&player_id).await
+                    .expect("Failed to get quests")
+                    .unwrap_or_default();
+                match quests.iter_mut().find(|quest| quest.id == quest_id) {
+                    Some(quest) => quest.completed = true,
+                    None => quests.push(QuestData {
+                        id: quest_id.clone(),
+                        title: definition.title,
+                        text: String::new(),
+                        completed: true,
+                        progress: 0,
+                    }),
+                }
+                self.state.player_quests.insert(&player_id, quests)
+                    .expect("Failed to save quests");
+
+                let balance = if definition.reward > 0 {
+                    self.post_entry(&player_id, LedgerEntry::new(LedgerEntryKind::QuestReward, definition.reward, quest_id.clone())).await?
+                } else {
+                    self.balance(&player_id).await
+                };
+
+                self.report_achievement(&player_id, format!("quest_{}", quest_id), json!({ "quest_id": quest_id }));
+                Ok(RpgGameResponse::Balance { balance })
+            }
+            RpgGameOperation::DefineItem { definition } => {
+                self.check_admin().await?;
+                if definition.stack_size == 0 {
+                    return Err(RpgGameError::InvalidItemDefinition(format!("item {} has a stack size of 0", definition.id)));
+                }
+
+                let id = definition.id.clone();
+                self.state.item_catalogue.insert(&id, definition)
+                    .expect("Failed to save item definition");
+                Ok(RpgGameResponse::Ok)
+            }
+            RpgGameOperation::AddItem { player_id, item_id, quantity, slot } => {
+                self.check_admin().await?;
+                self.check_trader(&player_id).await?;
+
+                let params = self.item_definition(&item_id).await?.params;
+                let mut inventory = self.inventory(&player_id).await;
+                let slot = inventory.add_item(item_id, quantity, params, slot)?;
+                self.check_catalogue(&inventory).await?;
+                self.state.player_inventories.insert(&player_id, inventory)
+                    .expect("Failed to save inventory");
+                Ok(RpgGameResponse::ItemPlaced { slot })
+            }
+            RpgGameOperation::RemoveItem { player_id, slot } => {
+                self.check_player_access(&player_id).await?;
+
+                let mut inventory = self.inventory(&player_id).await;
+                inventory.remove_item(&slot)?;
+                self.state.player_inventories.insert(&player_id, inventory)
+                    .expect("Failed to save inventory");
+                Ok(RpgGameResponse::Ok)
+            }
+            RpgGameOperation::MoveItem { player_id, from_slot, to_slot } => {
+                self.relocate_item(&player_id, &from_slot, SlotKind::Bag, Some(to_slot), SlotKind::Bag).await
+            }
+            RpgGameOperation::EquipItem { player_id, from_slot, equip_slot } => {
+                self.relocate_item(&player_id, &from_slot, SlotKind::Bag, Some(equip_slot), SlotKind::Equip).await
+            }
+            RpgGameOperation::UnequipItem { player_id, equip_slot, to_slot } => {
+                self.relocate_item(&player_id, &equip_slot, SlotKind::Equip, to_slot, SlotKind::Bag).await
+            }
+            RpgGameOperation::ProposeTrade {
+                player_id,
+                counterparty_id,
+                counterparty_chain,
+                offered,
+                requested,
+            } => {
+                self.check_player_access(&player_id).await?;
+
+                let chain_id = self.runtime.chain_id();
+                if offered.is_empty() && requested.is_empty() {
+                    return Err(RpgGameError::InvalidTrade("a trade must list at least one item".to_string()));
+                }
+                if counterparty_chain == chain_id && counterparty_id == player_id {
+                    return Err(RpgGameError::InvalidTrade("players cannot trade with themselves".to_string()));
+                }
+                if let Some(item) = offered.iter().chain(&requested).find(|item| item.quantity == 0) {
+                    return Err(RpgGameError::InvalidTrade(format!("item {} is listed without a quantity", item.item_id)));
+                }
+                // The proposer must be allowed to receive what they ask for
+                for item in &requested {
+                    self.check_tradable(&item.item_id).await?;
+                }
+                if counterparty_chain == chain_id {
+                    self.check_trader(&counterparty_id).await?;
+                }
+
+                // The offered items leave the bag for the escrow right away
+                let mut inventory = self.inventory(&player_id).await;
+                let escrow = self.take_trade_items(&mut inventory, &offered).await?;
+
+                // Prefixing the chain ID keeps trade IDs unique across chains
+                let nonce = *self.state.next_trade_nonce.get();
+                self.state.next_trade_nonce.set(nonce + 1);
+                let trade_id = format!("{}-{}", chain_id, nonce);
+
+                if counterparty_chain != chain_id {
+                    let proposal = RpgGameMessage::TradeProposed {
+                        trade_id: trade_id.clone(),
+                        proposer_id: player_id.clone(),
+                        counterparty_id: counterparty_id.clone(),
+                        offered: offered.clone(),
+                        requested: requested.clone(),
+                    };
+                    self.runtime
+                        .prepare_message(proposal)
+                        .with_authentication()
+                        .with_tracking()
+                        .send_to(counterparty_chain);
+                }
+
+                self.state.player_inventories.insert(&player_id, inventory)
+                    .expect("Failed to save inventory");
+                let created_at = self.runtime.system_time().micros();
+                self.save_trade(TradeOffer {
+                    trade_id: trade_id.clone(),
+                    proposer_id: player_id,
+                    proposer_chain: chain_id,
+                    counterparty_id,
+                    counterparty_chain,
+                    offered,
+                    requested,
+                    escrow,
+                    status: TradeStatus::Pending,
+                    created_at,
+                    failure_reason: None,
+                }).await;
+                Ok(RpgGameResponse::TradeProposed { trade_id })
+            }
+            RpgGameOperation::AcceptTrade { trade_id } => {
+                let mut trade = self.answerable_trade(&trade_id).await?;
+
+                let mut inventory = self.inventory(&trade.counterparty_id).await;
+                let requested = self.take_trade_items(&mut inventory, &trade.requested).await?;
+                if trade.proposer_chain == self.runtime.chain_id() {
+                    // Both players live on this chain, so the swap happens at once
+                    let mut proposer_inventory = self.inventory(&trade.proposer_id).await;
+                    inventory.put_items(std::mem::take(&mut trade.escrow))?;
+                    proposer_inventory.put_items(requested)?;
+                    self.state.player_inventories.insert(&trade.proposer_id, proposer_inventory)
+                        .expect("Failed to save inventory");
+                    trade.status = TradeStatus::Completed;
+                } else {
+                    // The proposer's chain settles by releasing the escrowed items to the counterparty
+                    let acceptance = RpgGameMessage::TradeAccepted {
+                        trade_id: trade_id.clone(),
+                        items: requested,
+                    };
+                    self.runtime
+                        .prepare_message(acceptance)
+                        .with_tracking()
+                        .send_to(trade.proposer_chain);
+                    trade.status = TradeStatus::Accepted;
+                }
+
+                self.state.player_inventories.insert(&trade.counterparty_id, inventory)
+                    .expect("Failed to save inventory");
+                self.state.trades.insert(&trade_id, trade)
+                    .expect("Failed to save trade");
+                Ok(RpgGameResponse::Ok)
+            }
+            RpgGameOperation::RejectTrade { trade_id } => {
+                let trade = self.answerable_trade(&trade_id).await?;
+                self.close_trade(trade, TradeStatus::Rejected).await?;
+                Ok(RpgGameResponse::Ok)
+            }
+            RpgGameOperation::CancelTrade { trade_id } => {
+                let trade = self.trade(&trade_id).await?;
+                if trade.proposer_chain != self.runtime.chain_id() {
+                    return Err(RpgGameError::InvalidTrade(format!("trade {} is cancelled on chain {}", trade_id, trade.proposer_chain)));
+                }
+                self.check_player_access(&trade.proposer_id).await?;
+                if trade.status != TradeStatus::Pending {
+                    return Err(RpgGameError::TradeNotPending(trade_id));
+                }
+
+                if trade.counterparty_chain == trade.proposer_chain {
+                    self.close_trade(trade, TradeStatus::Cancelled).await?;
+                } else {
+                    // The counterparty may be accepting right now, so its chain decides;
+                    // the escrow is released when it closes the trade
+                    let cancellation = RpgGameMessage::TradeCancelRequested { trade_id };
+                    self.runtime
+                        .prepare_message(cancellation)
+                        .send_to(trade.counterparty_chain);
+                }
+                Ok(RpgGameResponse::Ok)
+            }
+            RpgGameOperation::ClaimTradeItems { trade_id } => {
+                let mut trade = self.trade(&trade_id).await?;
+                let player_id = if trade.proposer_chain == self.runtime.chain_id() {
+                    trade.proposer_id.clone()
+                } else {
+                    trade.counterparty_id.clone()
+                };
+                self.check_player_access(&player_id).await?;
+                if matches!(trade.status, TradeStatus::Pending | TradeStatus::Accepted) {
+                    return Err(RpgGameError::InvalidTrade(format!("trade {} is still in progress", trade_id)));
+                }
+
+                let mut inventory = self.inventory(&player_id).await;
+                inventory.put_items(std::mem::take(&mut trade.escrow))?;
+                self.state.player_inventories.insert(&player_id, inventory)
+                    .expect("Failed to save inventory");
+                self.state.trades.insert(&trade_id, trade)
+                    .expect("Failed to save trade");
+                Ok(RpgGameResponse::Ok)
+            }
+            RpgGameOperation::SaveQuests { player_id, quests } => {
+                self.check_player_access(&player_id).await?;
+
+                // Only `CompleteQuest` completes catalogue quests, so a save cannot claim their rewards
+                for quest in &quests {
+                    if !self.state.quest_catalogue.contains_key(&quest.id).await.expect("Failed to get quest definition") {
+                        continue;
+                    }
+                    let completed = self.state.completed_quests.contains(&(player_id.clone(), quest.id.clone())).await
+                        .expect("Failed to get completed quests");
+                    if quest.completed != completed {
+                        return Err(RpgGameError::QuestCompletionMismatch {
+                            quest_id: quest.id.clone(),
+                            completed,
+                        });
+                    }
+                }
+
+                self.state.player_quests.insert(&player_id, quests)
+                    .expect("Failed to save quests");
+                Ok(RpgGameResponse::Ok)
+            }
+            RpgGameOperation::TransferPlayer {
+                player_id,
+                destination_chain,
+            } => {
+                let owner = self.check_player_access(&player_id).await?;
+
+                // Escrowed and undelivered trade items cannot follow the player
+                if let Some(trade_id) = self.open_trade(&player_id).await {
+                    return Err(RpgGameError::TradeInProgress { player_id, trade_id });
+                }
+
+                // The chain's own copy of the player is what gets transferred
+                let player_data = self.state.player_states.get(&player_id).await
+                    .expect("Failed to get player state")
+                    .ok_or_else(|| RpgGameError::MissingPlayerState(player_id.clone()))?;
+                let inventory_data = self.inventory(&player_id).await;
+                let quests_data = self.state.player_quests.get(&player_id).await
+                    .expect("Failed to get quests")
+                    .unwrap_or_default();
+
+                let player_state = PlayerState {
+                    health: player_data.health,
+                    max_health: player_data.max_health,
+                    strength: player_data.strength,
+                    wisdomness: player_data.wisdomness,
+                    benchpress: player_data.benchpress,
+                    curl: player_data.curl,
+                    experience: player_data.experience,
+                    level: player_data.level,
+                };
+                let inventory = serde_json::to_string(&inventory_data.items)
+                    .expect("Failed to serialize inventory");
+                let quests = serde_json::to_string(&quests_data)
+                    .expect("Failed to serialize quests");
+                let balance = self.balance(&player_id).await;
+
+                // Issue a one-time token bound to this player, owner, route, nonce and payload
+                let nonce = *self.state.next_transfer_nonce.get();
+                self.state.next_transfer_nonce.set(nonce + 1);
+                let auth_token = TransferTicket {
+                    player_id: player_id.clone(),
+                    owner,
+                    source_chain: self.runtime.chain_id(),
+                    destination_chain,
+                    nonce,
+                    player_state: player_state.clone(),
+                    inventory: inventory.clone(),
+                    quests: quests.clone(),
+                    balance,
+                }
+                .token();
+
+                // Transfer player to another chain by sending a cross-chain message
+                let transfer_message = RpgGameMessage::PlayerTransfer {
+                    player_id: player_id.clone(),
+                    player_state,
+                    inventory,
+                    quests,
+                    nonce,
+                    auth_token: auth_token.clone(),
+                    balance,
+                };
+
+                // The source copy is only deleted once the destination acknowledges the transfer;
+                // if the transfer is rejected or bounces, the player is unlocked again
+                let transfer_request = PlayerTransferRequest {
+                    source_chain: self.runtime.chain_id(),
+                    destination_chain,
+                    player_id: player_id.clone(),
+                    nonce,
+                    auth_token: auth_token.clone(),
+                    timestamp: self.runtime.system_time().micros(),
+                    status: TransferStatus::Pending,
+                    failure_reason: None,
+                    balance,
+                };
+
+                // The balance travels with the player and is refunded if the transfer fails
+                if balance > 0 {
+                    let departure = LedgerEntry {
+                        counterparty_chain: Some(destination_chain),
+                        ..LedgerEntry::new(LedgerEntryKind::ChainDeparture, balance, auth_token.clone())
+                    };
+                    self.post_entry(&player_id, departure).await?;
+                }
+
+                self.state.player_transfer_requests.insert(&player_id, transfer_request)
+                    .expect("Failed to save player transfer request");
+
+                // Send the player data to the destination chain
+                self.runtime
+                    .prepare_message(transfer_message)
+                    .with_authentication()
+                    .with_tracking()
+                    .send_to(destination_chain);
+                Ok(RpgGameResponse::TransferStarted { nonce, auth_token })
+            }
+            RpgGameOperation::JoinGuild {
+                player_id,
+                guild_id,
+                chain_id,
+            } => {
+                self.check_player_access(&player_id).await?;
+
+                // Send a cross-chain message to join a guild on another chain
+                let join_message = RpgGameMessage::GuildJoinRequest {
+                    player_id: player_id.clone(),
+                    guild_id: guild_id.clone(),
+                };
+
+                self.runtime
+                    .prepare_message(join_message)
+                    .with_authentication()
+                    .send_to(chain_id);
+
+                // Add to join request list temporarily
+                if let Some(requests) = self.state.guild_join_requests.get_mut(&guild_id).await.expect("Failed to get guild join requests") {
+                    requests.push(player_id);
+                } else {
+                    self.state.guild_join_requests.insert(&guild_id, vec![player_id])
+                        .expect("Failed to save guild join request");
+                }
+                Ok(RpgGameResponse::Ok)
+            }
+            RpgGameOperation::RecordBattle {
+                battle_id,
+                player_id,
+                opponent,
+                player_result,
+                damage_dealt,
+                damage_taken,
+                experience_gained,
+            } => {
+                self.check_player_access(&player_id).await?;
+
+                if player_result > 2 {
+                    return Err(RpgGameError::InvalidBattleResult(player_result));
+                }
+                if self.state.battle_records.contains_key(&battle_id).await.expect("Failed to get battle record") {
+                    return Err(RpgGameError::DuplicateBattle(battle_id));
+                }
+
+                // A player cannot survive more damage than their effective health allows
+                let player_stats = self.state.effective_stats(&player_id).await;
+                if let Some(stats) = &player_stats {
+                    if player_result != 0 && damage_taken >= stats.max_health {
+                        return Err(RpgGameError::ImplausibleBattle {
+                            damage_taken,
+                            max_health: stats.max_health,
+                        });
+                    }
+                }
+
+                // Create a battle record
+                let battle_record = BattleRecord {
+                    battle_id: battle_id.clone(),
+                    player_id: player_id.clone(),
+                    opponent,
+                    result: player_result,
+                    damage_dealt,
+                    damage_taken,
+                    experience_gained,
+                    timestamp: self.runtime.system_time().micros(),
+                    player_stats,
+                };
+
+                // Save the battle record
+                self.state.battle_records.insert(&battle_id, battle_record)
+                    .expect("Failed to save battle record");
+
+                // Add to player's battle history
+                if let Some(battles) = self.state.player_battles.get_mut(&player_id).await.expect("Failed to get player battles") {
+                    battles.push(battle_id.clone());
+                } else {
+                    self.state.player_battles.insert(&player_id, vec![battle_id.clone()])
+                        .expect("Failed to save player battle history");
+                }
+
+                // Track consecutive wins; any other result ends the streak
+                let streak = if player_result == 2 {
+                    self.state.player_win_streaks.get(&player_id).await
+                        .expect("Failed to get win streak")
+                        .unwrap_or(0) + 1
+                } else {
+                    0
+                };
+                self.state.player_win_streaks.insert(&player_id, streak)
+                    .expect("Failed to save win streak");
+
+                if player_result == 2 {
+                    let level = self.state.player_states.get(&player_id).await
+                        .expect("Failed to get player state")
+                        .map_or(1, |player_data| player_data.level);
+                    self.report_progress(&player_id, level, 1);
+                }
+
+                if progression::WIN_STREAK_MILESTONES.contains(&streak) {
+                    self.report_achievement(&player_id, format!("win_streak_{}", streak), json!({ "battle_id": battle_id, "streak": streak }));
+                }
+                Ok(RpgGameResponse::Ok)
+            }
+            RpgGameOperation::RewardBattle { battle_id, amount } => {
+                self.check_admin().await?;
+                if amount == 0 {
+                    return Err(RpgGameError::InvalidAmount);
+                }
+
+                let player_id = self.state.battle_records.get(&battle_id).await
+                    .expect("Failed to get battle record")
+                    .ok_or_else(|| RpgGameError::UnknownBattle(battle_id.clone()))?
+                    .player_id;
+                self.check_trader(&player_id).await?;
+                if self.state.rewarded_battles.contains(&battle_id).await.expect("Failed to get rewarded battles") {
+                    return Err(RpgGameError::BattleAlreadyRewarded(battle_id));
+                }
+
+                self.state.rewarded_battles.insert(&battle_id)
+                    .expect("Failed to save rewarded battle");
+                let balance = self.post_entry(&player_id, LedgerEntry::new(LedgerEntryKind::BattleReward, amount, battle_id)).await?;
+                Ok(RpgGameResponse::Balance { balance })
+            }
+        }
+    }
+
+    /// Reports a milestone to the hub application, if this world chain is connected to one.
+    fn report_achievement(&mut self, player_id: &str, achievement_id: String, mut metadata: serde_json::Value) {
+        let Some((hub_application_id, hub_chain_id)) = self.hub() else {
+            return;
+        };
+
+        metadata["region"] = self.runtime.application_parameters().world_region.into();
+        let operation = HubOperation::ReportAchievement {
+            hub_chain_id,
+            player_id: player_id.to_string(),
+            achievement_id,
+            metadata: metadata.to_string(),
+        };
+        // Authenticated, so the hub knows the report comes from this application
+        if let Err(e) = self.runtime.call_application(true, hub_application_id, &operation) {
+            log::warn!("Failed to report achievement for player {}: {}", player_id, e);
+        }
+    }
+
+    /// Reports a player's level and new battle wins to the hub leaderboards, if this world chain is connected to one.
+    fn report_progress(&mut self, player_id: &str, level: u64, battle_wins_gained: u64) {
+        let Some((hub_application_id, hub_chain_id)) = self.hub() else {
+            return;
+        };
+
+        let operation = HubOperation::ReportPlayerProgress {
+            hub_chain_id,
+            player_id: player_id.to_string(),
+            level,
+            battle_wins_gained,
+        };
+        if let Err(e) = self.runtime.call_application(true, hub_application_id, &operation) {
+            log::warn!("Failed to report progress for player {}: {}", player_id, e);
+        }
+    }
+
+    /// Returns the hub application and hub chain configured for this world chain.
+    fn hub(&mut self) -> Option<(ApplicationId<RpgHubAbi>, ChainId)> {
+        let params = self.runtime.application_parameters();
+        params.hub_application_id.zip(params.hub_chain_id)
+    }
+
+    /// Returns the stored inventory of `player_id`, empty if none was saved yet.
+    async fn inventory(&self, player_id: &str) -> InventoryData {
+        self.state.player_inventories.get(player_id).await
+            .expect("Failed to get inventory")
+            .unwrap_or_default()
+    }
+
+    /// Returns the catalogue's definition of `item_id`.
+    async fn item_definition(&self, item_id: &str) -> Result<ItemDefinition, RpgGameError> {
+        self.state.item_catalogue.get(item_id).await
+            .expect("Failed to get item definition")
+            .ok_or_else(|| RpgGameError::UnknownItem(item_id.to_string()))
+    }
+
+    /// Checks every item of `inventory` against the item catalogue.
+    async fn check_catalogue(&self, inventory: &InventoryData) -> Result<(), RpgGameError> {
+        for item in &inventory.items {
+            self.item_definition(&item.item_id).await?.check(item)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the block is signed by an admin.
+    ///
+    /// `Parameters::admins` are admins on every chain of the application; the account that created it is only
+    /// known, and an admin, on the creator chain.
+    async fn check_admin(&mut self) -> Result<(), RpgGameError> {
+        let signer = self.runtime.authenticated_signer()
+            .ok_or(RpgGameError::MissingSigner)?;
+        if self.runtime.application_parameters().admins.contains(&signer) {
+            return Ok(());
+        }
+        if !self.state.admins.contains(&signer).await.expect("Failed to get admin") {
+            return Err(RpgGameError::NotAdmin);
+        }
+        Ok(())
+    }
+
+    /// Moves one of the player's items from a `from` slot to a free `to` slot.
+    async fn relocate_item(
+        &mut self,
+        player_id: &str,
+        from_slot: &str,
+        from: SlotKind,
+        to_slot: Option<String>,
+        to: SlotKind,
+    ) -> Result<RpgGameResponse, RpgGameError> {
+        self.check_player_access(player_id).await?;
+
+        let mut inventory = self.inventory(player_id).await;
+        let slot = inventory.relocate_item(from_slot, from, to_slot, to)?;
+        self.check_catalogue(&inventory).await?;
+        self.state.player_inventories.insert(player_id, inventory)
+            .expect("Failed to save inventory");
+        Ok(RpgGameResponse::ItemPlaced { slot })
+    }
+
+    /// Returns the currency balance of `player_id`.
+    async fn balance(&self, player_id: &str) -> u64 {
+        self.state.balances.get(player_id).await
+            .expect("Failed to get balance")
+            .unwrap_or(0)
+    }
+
+    /// Checks that `amount` can be credited to `player_id` without overflowing the balance.
+    async fn check_credit(&self, player_id: &str, amount: u64) -> Result<(), RpgGameError> {
+        self.balance(player_id).await.checked_add(amount)
+            .map(|_| ())
+            .ok_or_else(|| RpgGameError::BalanceOverflow(player_id.to_string()))
+    }
+
+    /// Checks that `player_id` lives on this chain and can receive `amount`.
+    async fn check_payee(&self, player_id: &str, amount: u64) -> Result<(), RpgGameError> {
+        self.check_trader(player_id).await?;
+        self.check_credit(player_id, amount).await
+    }
+
+    /// Applies `entry` to the balance of `player_id` with checked arithmetic and appends it to the player's history.
+    ///
+    /// Returns the new balance; nothing is written when the entry is refused.
+    async fn post_entry(&mut self, player_id: &str, mut entry: LedgerEntry) -> Result<u64, RpgGameError> {
+        let balance = self.balance(player_id).await;
+        let balance = if entry.kind.is_credit() {
+            balance.checked_add(entry.amount)
+                .ok_or_else(|| RpgGameError::BalanceOverflow(player_id.to_string()))?
+        } else {
+            balance.checked_sub(entry.amount)
+                .ok_or_else(|| RpgGameError::InsufficientFunds {
+                    player_id: player_id.to_string(),
+                    balance,
+                    amount: entry.amount,
+                })?
+        };
+
+        entry.balance = balance;
+        entry.timestamp = self.runtime.system_time().micros();
+        self.state.balances.insert(player_id, balance)
+            .expect("Failed to save balance");
+        if let Some(history) = self.state.currency_history.get_mut(player_id).await.expect("Failed to get currency history") {
+            history.push(entry);
+        } else {
+            self.state.currency_history.insert(player_id, vec![entry])
+                .expect("Failed to save currency history");
+        }
+        Ok(balance)
+    }
+
+    /// Returns the shop `shop_id` of this region.
+    async fn shop(&self, shop_id: &str) -> Result<Shop, RpgGameError> {
+        self.state.shops.get(shop_id).await
+            .expect("Failed to get shop")
+            .ok_or_else(|| RpgGameError::UnknownShop(shop_id.to_string()))
+    }
+
+    /// Returns the trade `trade_id`.
+    async fn trade(&self, trade_id: &str) -> Result<TradeOffer, RpgGameError> {
+        self.state.trades.get(trade_id).await
+            .expect("Failed to get trade")
+            .ok_or_else(|| RpgGameError::UnknownTrade(trade_id.to_string()))
+    }
+
+    /// Returns a pending trade the block's signer can accept or reject as the counterparty.
+    async fn answerable_trade(&mut self, trade_id: &str) -> Result<TradeOffer, RpgGameError> {
+        let trade = self.trade(trade_id).await?;
+        if trade.counterparty_chain != self.runtime.chain_id() {
+            return Err(RpgGameError::InvalidTrade(format!("trade {} is answered on chain {}", trade_id, trade.counterparty_chain)));
+        }
+        self.check_player_access(&trade.counterparty_id).await?;
+        if trade.status != TradeStatus::Pending {
+            return Err(RpgGameError::TradeNotPending(trade_id.to_string()));
+        }
+        Ok(trade)
+    }
+
+    /// Stores a new trade and lists it for the players of this chain taking part in it.
+    async fn save_trade(&mut self, trade: TradeOffer) {
+        let chain_id = self.runtime.chain_id();
+        let players = [
+            (trade.proposer_chain, &trade.proposer_id),
+            (trade.counterparty_chain, &trade.counterparty_id),
+        ];
+        for (player_chain, player_id) in players {
+            if player_chain != chain_id {
+                continue;
+            }
+            if let Some(trades) = self.state.player_trades.get_mut(player_id).await.expect("Failed to get player trades") {
+                trades.push(trade.trade_id.clone());
+            } else {
+                self.state.player_trades.insert(player_id, vec![trade.trade_id.clone()])
+                    .expect("Failed to save player trades");
+            }
+        }
+
+        let trade_id = trade.trade_id.clone();
+        self.state.trades.insert(&trade_id, trade)
+            .expect("Failed to save trade");
+    }
+
+    /// Returns a trade that still involves `player_id` on this chain, if any.
+    async fn open_trade(&self, player_id: &str) -> Option<String> {
+        let trade_ids = self.state.player_trades.get(player_id).await
+            .expect("Failed to get player trades")
+            .unwrap_or_default();
+        for trade_id in trade_ids {
+            let trade = self.state.trades.get(&trade_id).await
+                .expect("Failed to get trade");
+            if trade.is_some_and(|trade| trade.is_open()) {
+                return Some(trade_id);
+            }
+        }
+        None
+    }
+
+    /// Checks that `item_id` is in the catalogue and may change hands.
+    async fn check_tradable(&self, item_id: &str) -> Result<(), RpgGameError> {
+        let definition = self.state.item_catalogue.get(item_id).await
+            .expect("Failed to get item definition")
+            .ok_or_else(|| RpgGameError::UnknownItem(item_id.to_string()))?;
+        if !definition.tradable {
+            return Err(RpgGameError::ItemNotTradable(item_id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Checks a trade proposed from another chain before it is offered to `counterparty_id`.
+    async fn check_incoming_trade(
+        &self,
+        counterparty_id: &str,
+        offered: &[TradeItem],
+        requested: &[TradeItem],
+    ) -> Result<(), RpgGameError> {
+        self.check_trader(counterparty_id).await?;
+        for item in offered.iter().chain(requested) {
+            self.check_tradable(&item.item_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Takes the items listed on one side of a trade out of `inventory`.
+    async fn take_trade_items(
+        &self,
+        inventory: &mut InventoryData,
+        items: &[TradeItem],
+    ) -> Result<Vec<InventoryItem>, RpgGameError> {
+        let mut taken = Vec::new();
+        for item in items {
+            self.check_tradable(&item.item_id).await?;
+            taken.extend(inventory.take_items(&item.item_id, item.quantity)?);
+        }
+        Ok(taken)
+    }
+
+    /// Puts trade items in the bag of `player_id`.
+    ///
+    /// Returns the items if they do not all fit, so the trade keeps them until the player claims them.
+    async fn deliver_trade_items(&mut self, player_id: &str, items: Vec<InventoryItem>) -> Vec<InventoryItem> {
+        let mut inventory = self.inventory(player_id).await;
+        if let Err(error) = inventory.put_items(items.clone()) {
+            log::info!("Kept trade items of player {} in escrow: {}", player_id, error);
+            return items;
+        }
+        self.state.player_inventories.insert(player_id, inventory)
+            .expect("Failed to save inventory");
+        Vec::new()
+    }
+
+    /// Ends a pending trade without a swap and returns the escrowed items to the proposer.
+    ///
+    /// When the proposer lives on another chain, its chain is told to release the escrow.
+    async fn close_trade(&mut self, mut trade: TradeOffer, status: TradeStatus) -> Result<(), RpgGameError> {
+        if trade.proposer_chain == self.runtime.chain_id() {
+            let mut inventory = self.inventory(&trade.proposer_id).await;
+            inventory.put_items(std::mem::take(&mut trade.escrow))?;
+            self.state.player_inventories.insert(&trade.proposer_id, inventory)
+                .expect("Failed to save inventory");
+        } else {
+            let closure = RpgGameMessage::TradeClosed {
+                trade_id: trade.trade_id.clone(),
+                status,
+                failure_reason: None,
+            };
+            self.runtime
+                .prepare_message(closure)
+                .send_to(trade.proposer_chain);
+        }
+
+        trade.status = status;
+        let trade_id = trade.trade_id.clone();
+        self.state.trades.insert(&trade_id, trade)
+            .expect("Failed to save trade");
+        Ok(())
+    }
+
+    /// Ends a pending trade the counterparty's chain closed or never received, returning the escrow to the proposer.
+    async fn release_escrow(&mut self, trade_id: &str, status: TradeStatus, failure_reason: Option<String>) {
+        let Some(mut trade) = self.state.trades.get(trade_id).await.expect("Failed to get trade") else {
+            log::warn!("No pending trade {}", trade_id);
+            return;
+        };
+
+        if trade.status != TradeStatus::Pending {
+            log::warn!("Ignored closure of trade {}: it is no longer pending", trade_id);
+            return;
+        }
+
+        log::info!("Trade {} ended as {:?}", trade_id, status);
+        trade.status = status;
+        trade.failure_reason = failure_reason;
+        trade.escrow = self.deliver_trade_items(&trade.proposer_id, std::mem::take(&mut trade.escrow)).await;
+        self.state.trades.insert(trade_id, trade)
+            .expect("Failed to save trade");
+    }
+
+    /// Refuses an incoming player transfer and reports the reason back to the source chain.
+    fn reject_transfer(&mut self, source_chain: ChainId, player_id: String, nonce: u64, error: RpgGameError) {
+        log::warn!("Rejected transfer of player {}: {}", player_id, error);
+
+        let rejection = RpgGameMessage::PlayerTransferRejected {
+            player_id,
+            nonce,
+            error,
+        };
+        self.runtime
+            .prepare_message(rejection)
+            .send_to(source_chain);
+    }
+
+    /// Marks the pending transfer of `player_id` as failed, which unlocks the player on this chain.
+    async fn fail_transfer(&mut self, player_id: &str, nonce: u64, error: RpgGameError) {
+        let Some(request) = self.state.player_transfer_requests.get_mut(player_id).await.expect("Failed to get player transfer request") else {
+            log::warn!("No pending transfer for player {}", player_id);
+            return;
+        };
+
+        if request.nonce != nonce || request.status != TransferStatus::Pending {
+            log::warn!("Ignored transfer failure for player {}: no matching transfer", player_id);
+            return;
+        }
+
+        log::warn!("Transfer of player {} failed: {}", player_id, error);
+        request.status = TransferStatus::Failed;
+        request.failure_reason = Some(error.to_string());
+
+        // The player stays, and so does their balance
+        let refund = LedgerEntry {
+            counterparty_chain: Some(request.destination_chain),
+            ..LedgerEntry::new(LedgerEntryKind::Refund, request.balance, request.auth_token.clone())
+        };
+        if refund.amount > 0 {
+            if let Err(error) = self.post_entry(player_id, refund).await {
+                log::error!("Failed to refund the balance of player {}: {}", player_id, error);
+            }
+        }
+    }
+
+    /// Marks the pending currency transfer `transfer_id` as failed and refunds the sender.
+    async fn fail_currency_transfer(&mut self, transfer_id: &str, error: RpgGameError) {
+        let Some(mut transfer) = self.state.currency_transfers.get(transfer_id).await.expect("Failed to get currency transfer") else {
+            log::warn!("No pending currency transfer {}", transfer_id);
+            return;
+        };
+
+        if transfer.status != TransferStatus::Pending {
+            log::warn!("Ignored failure of currency transfer {}: no matching transfer", transfer_id);
+            return;
+        }
+
+        log::warn!("Currency transfer {} failed: {}", transfer_id, error);
+        transfer.status = TransferStatus::Failed;
+        transfer.failure_reason = Some(error.to_string());
+
+        let refund = LedgerEntry {
+            counterparty: Some(transfer.recipient_id.clone()),
+            counterparty_chain: Some(transfer.recipient_chain),
+            ..LedgerEntry::new(LedgerEntryKind::Refund, transfer.amount, transfer_id.to_string())
+        };
+        if let Err(error) = self.post_entry(&transfer.sender_id, refund).await {
+            log::error!("Failed to refund currency transfer {}: {}", transfer_id, error);
+        }
+        self.state.currency_transfers.insert(transfer_id, transfer)
+            .expect("Failed to save currency transfer");
+    }
+
+    /// Checks a submitted player state against the configured `SaveRules`.
+    fn check_save_rules(
+        rules: &SaveRules,
+        recorded: Option<&PlayerData>,
+        submitted: &PlayerData,
+        equipment: &StatModifiers,
+    ) -> Result<(), RpgGameError> {
+        // Equipped items can raise (or lower) the health cap
+        let max_health = submitted.effective_stats(equipment).max_health;
+        if rules.health_within_max && submitted.health > max_health {
+            return Err(RpgGameError::SaveRuleViolated {
+                invariant: SaveInvariant::HealthWithinMax,
+                details: format!("health {} exceeds effective max_health {}", submitted.health, max_health),
+            });
+        }
+
+        if rules.authoritative_progression {
+            return Self::check_progression(recorded, submitted);
+        }
+
+        // The first save of a character is compared with a new level 1 character
+        let new_character = PlayerData {
+            health: progression::STARTING_MAX_HEALTH,
+            max_health: progression::STARTING_MAX_HEALTH,
+            strength: progression::STARTING_STRENGTH,
+            wisdomness: progression::STARTING_WISDOMNESS,
+            benchpress: progression::STARTING_BENCHPRESS,
+            curl: progression::STARTING_CURL,
+            experience: 0,
+            level: 1,
+        };
+        let recorded = recorded.unwrap_or(&new_character);
+
+        if rules.monotonic_progression {
+            if submitted.experience < recorded.experience {
+                return Err(RpgGameError::SaveRuleViolated {
+                    invariant: SaveInvariant::ExperienceNotDecreasing,
+                    details: format!("experience went from {} to {}", recorded.experience, submitted.experience),
+                });
+            }
+            if submitted.level < recorded.level {
+                return Err(RpgGameError::SaveRuleViolated {
+                    invariant: SaveInvariant::LevelNotDecreasing,
+                    details: format!("level went from {} to {}", recorded.level, submitted.level),
+                });
+            }
+        }
+
+        let levels_gained = submitted.level.saturating_sub(recorded.level);
+        if levels_gained > rules.max_levels_per_save {
+            return Err(RpgGameError::SaveRuleViolated {
+                invariant: SaveInvariant::MaxLevelsPerSave,
+                details: format!("gained {} levels, at most {} allowed", levels_gained, rules.max_levels_per_save),
+            });
+        }
+
+        let stats = [
+            ("strength", recorded.strength, submitted.strength, progression::STRENGTH_PER_LEVEL),
+            ("wisdomness", recorded.wisdomness, submitted.wisdomness, progression::WISDOMNESS_PER_LEVEL),
+            ("benchpress", recorded.benchpress, submitted.benchpress, progression::BENCHPRESS_PER_LEVEL),
+            ("curl", recorded.curl, submitted.curl, progression::CURL_PER_LEVEL),
+        ];
+        for (stat, before, after, per_level) in stats {
+            let allowed = per_level.saturating_mul(rules.max_levels_per_save);
+            let gained = after.saturating_sub(before);
+            if gained > allowed {
+                return Err(RpgGameError::SaveRuleViolated {
+                    invariant: SaveInvariant::MaxStatGainPerSave,
+                    details: format!("{} grew by {}, at most {} allowed", stat, gained, allowed),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that a submitted player state keeps the progression recorded on chain.
+    ///
+    /// Experience, level and stats only change through `AddExperience`, so they must match the
+    /// record; a new character starts at level 1 without experience and with the starting stats.
+    fn check_progression(recorded: Option<&PlayerData>, submitted: &PlayerData) -> Result<(), RpgGameError> {
+        let checks = match recorded {
+            Some(recorded) => vec![
+                ("experience", recorded.experience, submitted.experience),
+                ("level", recorded.level, submitted.level),
+                ("strength", recorded.strength, submitted.strength),
+                ("wisdomness", recorded.wisdomness, submitted.wisdomness),
+                ("benchpress", recorded.benchpress, submitted.benchpress),
+                ("curl", recorded.curl, submitted.curl),
+            ],
+            None => vec![
+                ("experience", 0, submitted.experience),
+                ("level", 1, submitted.level),
+                ("max_health", progression::STARTING_MAX_HEALTH, submitted.max_health),
+                ("strength", progression::STARTING_STRENGTH, submitted.strength),
+                ("wisdomness", progression::STARTING_WISDOMNESS, submitted.wisdomness),
+                ("benchpress", progression::STARTING_BENCHPRESS, submitted.benchpress),
+                ("curl", progression::STARTING_CURL, submitted.curl),
+            ],
+        };
+
+        for (field, expected, actual) in checks {
+            if expected != actual {
+                return Err(RpgGameError::InconsistentProgression {
+                    field: field.to_string(),
+                    expected,
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the block's signer owns `player_id` and that the player can be edited on this chain.
+    ///
+    /// Returns the owner on success.
+    async fn check_player_access(&mut self, player_id: &str) -> Result<AccountOwner, RpgGameError> {
+        let signer = self.runtime.authenticated_signer()
+            .ok_or(RpgGameError::MissingSigner)?;
+
+        let owner = self.state.player_owners.get(player_id).await
+            .expect("Failed to get player owner")
+            .ok_or_else(|| RpgGameError::UnknownPlayer(player_id.to_string()))?;
+        if owner != signer {
+            return Err(RpgGameError::Unauthorized(player_id.to_string()));
+        }
+
+        self.check_not_locked(player_id).await?;
+        Ok(owner)
+    }
+
+    /// Checks that `player_id` is registered and lives on this chain, e.g. to receive a trade.
+    async fn check_trader(&self, player_id: &str) -> Result<(), RpgGameError> {
+        if !self.state.player_owners.contains_key(player_id).await.expect("Failed to get player owner") {
+            return Err(RpgGameError::UnknownPlayer(player_id.to_string()));
+        }
+        self.check_not_locked(player_id).await
+    }
+
+    /// Checks that `player_id` is not leaving or gone from this chain.
+    async fn check_not_locked(&self, player_id: &str) -> Result<(), RpgGameError> {
+        // A pending or completed transfer means the player is in flight or lives on another chain
+        let request = self.state.player_transfer_requests.get(player_id).await
+            .expect("Failed to get player transfer request");
+        if request.is_some_and(|request| request.status != TransferStatus::Failed) {
+            return Err(RpgGameError::TransferLocked(player_id.to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -1,29 +1,107 @@
 use async_graphql::{Request, Response};
-use linera_sdk::linera_base_types::{ContractAbi, ServiceAbi, ChainId, ApplicationId};
+use linera_sdk::linera_base_types::{AccountOwner, ApplicationId, ContractAbi, ServiceAbi, ChainId};
 use serde::{Serialize, Deserialize};
 
 pub struct RpgHubAbi;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HubParameters {
+    /// Size limit of the achievement catalogue, and so of the achievements a single player can earn
     pub max_achievements: u64,
+    /// Owners allowed to administer the hub from the start; the account that creates the hub is always one
+    #[serde(default)]
+    pub admins: Vec<AccountOwner>,
+}
+
+/// An achievement players can earn, as registered in the hub's catalogue
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, async_graphql::SimpleObject, async_graphql::InputObject)]
+#[graphql(input_name = "AchievementDefinitionInput")]
+pub struct AchievementDefinition {
+    pub id: String,
+    pub title: String,
+    pub points: u64,
+    pub rarity: AchievementRarity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, async_graphql::Enum)]
+pub enum AchievementRarity {
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+/// How long a world chain may go without a heartbeat before the hub lists it as stale (10 minutes)
+pub const DEFAULT_HEARTBEAT_TIMEOUT_MICROS: u64 = 10 * 60 * 1_000_000;
+
+/// Number of top players each hub leaderboard keeps
+pub const LEADERBOARD_SIZE: u64 = 100;
+
+/// Rankings the hub keeps across all world chains
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, async_graphql::Enum)]
+pub enum LeaderboardKind {
+    AchievementPoints,
+    Level,
+    BattleWins,
+}
+
+impl LeaderboardKind {
+    pub const ALL: [LeaderboardKind; 3] = [
+        LeaderboardKind::AchievementPoints,
+        LeaderboardKind::Level,
+        LeaderboardKind::BattleWins,
+    ];
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum HubOperation {
-    /// Submit an achievement from a world chain to the hub
-    SubmitAchievement {
+    /// Register a world chain with the hub, and the game application allowed to report from it
+    RegisterWorldChain {
+        chain_id: ChainId,
+        world_region: String,
+        game_application_id: ApplicationId,
+    },
+    /// Add an achievement definition to the catalogue
+    RegisterAchievement {
+        definition: AchievementDefinition,
+    },
+    /// Forward an achievement earned on this world chain to the hub chain; only callable by the game application
+    ReportAchievement {
+        hub_chain_id: ChainId,
         player_id: String,
         achievement_id: String,
-        chain_id: ChainId,
-        timestamp: u64,
-        metadata: String,  // JSON string with achievement details
+        metadata: String,
     },
-    /// Register a world chain with the hub
-    RegisterWorldChain {
+    /// Forward a player's progress on this world chain to the hub chain's leaderboards; only callable by the game application
+    ReportPlayerProgress {
+        hub_chain_id: ChainId,
+        player_id: String,
+        level: u64,
+        battle_wins_gained: u64,
+    },
+    /// Grant an owner the right to administer the hub
+    AddAdmin {
+        owner: AccountOwner,
+    },
+    /// Revoke an owner's admin rights; the last admin cannot be removed
+    RemoveAdmin {
+        owner: AccountOwner,
+    },
+    /// Tell the hub chain this world chain is still alive
+    SendHeartbeat {
+        hub_chain_id: ChainId,
+    },
+    /// Mark a world chain as retired; registering it again reactivates it
+    DeactivateWorldChain {
         chain_id: ChainId,
-        world_region: String,
     },
+    /// Open a new season; leaderboards accumulate into it until it ends
+    StartSeason {
+        name: String,
+    },
+    /// Archive the current season's leaderboards and reset the seasonal counters
+    EndSeason,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,19 +111,72 @@ pub enum HubMessage {
         player_id: String,
         achievement_id: String,
         chain_id: ChainId,
+        /// Game application that reported the achievement
+        application_id: ApplicationId,
         timestamp: u64,
         metadata: String,
     },
+    /// Player progress from a world chain
+    PlayerProgressReported {
+        player_id: String,
+        chain_id: ChainId,
+        /// Game application that reported the progress
+        application_id: ApplicationId,
+        level: u64,
+        battle_wins_gained: u64,
+        timestamp: u64,
+    },
     /// World chain registration
     WorldChainRegistered {
         chain_id: ChainId,
         world_region: String,
+        game_application_id: ApplicationId,
+    },
+    /// Liveness signal from the world chain the message originates from; the hub chain times it on receipt
+    Heartbeat,
+}
+
+/// Reasons a [`HubOperation`] or achievement submission is rejected
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+pub enum HubError {
+    #[error("achievement {0} is not in the catalogue")]
+    UnknownAchievement(String),
+    #[error("achievement {0} is already in the catalogue")]
+    AchievementAlreadyDefined(String),
+    #[error("player {player_id} already earned achievement {achievement_id}")]
+    DuplicateAchievement {
+        player_id: String,
+        achievement_id: String,
     },
+    #[error("the achievement catalogue is full ({0} achievements)")]
+    CatalogueFull(u64),
+    #[error("this operation must run on the hub chain")]
+    NotHubChain,
+    #[error("world chain {0} is not registered")]
+    UnknownWorldChain(ChainId),
+    #[error("world chain {0} is deactivated")]
+    InactiveWorldChain(ChainId),
+    #[error("message claims to come from chain {0} but was sent by another chain")]
+    OriginMismatch(ChainId),
+    #[error("reports must be made by a game application")]
+    MissingCaller,
+    #[error("reports from world chain {0} must come from its registered game application")]
+    ApplicationMismatch(ChainId),
+    #[error("the operation is not signed")]
+    MissingSigner,
+    #[error("{0} is not a hub admin")]
+    Unauthorized(AccountOwner),
+    #[error("the hub must keep at least one admin")]
+    LastAdmin,
+    #[error("season {0} is still running")]
+    SeasonInProgress(u64),
+    #[error("no season is running")]
+    NoActiveSeason,
 }
 
 impl ContractAbi for RpgHubAbi {
     type Operation = HubOperation;
-    type Response = ();
+    type Response = Result<(), HubError>;
 }
 
 impl ServiceAbi for RpgHubAbi {
@@ -1,15 +1,15 @@
 // RPG Hub Contract
 // SPDX-License-Identifier: MIT
 
-#[cfg_attr(target_arch = "wasm32", no_main)]
-use crate::hub_abi::{HubOperation, HubMessage, RpgHubAbi};
-use crate::hub_state::{HubState, PlayerAchievement, WorldChainInfo};
+#![cfg_attr(target_arch = "wasm32", no_main)]
+
+use rpg_game::hub_abi::{HubError, HubOperation, HubMessage, HubParameters, LeaderboardKind, RpgHubAbi};
+use rpg_game::hub_state::{AchievementRecord, HubState, PlayerAchievement, SeasonArchive, SeasonInfo, WorldChainInfo};
 use linera_sdk::{
-    linera_base_types::WithContractAbi,
+    linera_base_types::{ApplicationId, ChainId, WithContractAbi},
     views::{RootView, View},
     Contract, ContractRuntime,
 };
-use linera_sdk::log;
 
 pub struct RpgHubContract {
     state: HubState,
@@ -25,7 +25,7 @@ impl WithContractAbi for RpgHubContract {
 impl Contract for RpgHubContract {
     type Message = HubMessage;
     type InstantiationArgument = u64; // max_achievements
-    type Parameters = crate::hub_abi::HubParameters;
+    type Parameters = HubParameters;
     type EventValue = ();
 
     async fn load(runtime: ContractRuntime<Self>) -> Self {
@@ -37,92 +37,185 @@ impl Contract for RpgHubContract {
 
     async fn instantiate(&mut self, max_achievements: u64) {
         // Validate that the application parameters were configured correctly
-        let params = self.runtime.application_parameters::<hub_abi::HubParameters>();
-        if params.is_err() || params.unwrap().max_achievements != max_achievements {
-            linera_sdk::log::warn!("Max achievements parameter mismatch");
+        let params = self.runtime.application_parameters();
+        if params.max_achievements != max_achievements {
+            log::warn!("Max achievements parameter mismatch");
         }
         
         // Initialize counters
         self.state.total_chains.set(0);
         self.state.total_achievements.set(0);
+        self.state.catalogue_size.set(0);
+        self.state.next_season_id.set(1);
+        self.state.scoring_period.set(0);
+
+        // The listed admins, plus whoever created the hub
+        for owner in params.admins.iter().copied().chain(self.runtime.authenticated_signer()) {
+            self.state.admins.insert(&owner).expect("Failed to add admin");
+        }
     }
 
-    async fn execute_operation(&mut self, operation: HubOperation) -> () {
+    async fn execute_operation(&mut self, operation: HubOperation) -> Result<(), HubError> {
         match operation {
-            HubOperation::SubmitAchievement {
+            HubOperation::RegisterWorldChain {
+                chain_id,
+                world_region,
+                game_application_id,
+            } => {
+                self.check_admin().await?;
+                self.register_world_chain(chain_id, world_region.clone(), game_application_id).await;
+
+                log::info!("World chain {} registered with region {}", chain_id, world_region);
+                Ok(())
+            }
+            HubOperation::RegisterAchievement { definition } => {
+                self.check_admin().await?;
+                if self.state.achievement_catalogue.contains_key(&definition.id).await.expect("Failed to get achievement definition") {
+                    return Err(HubError::AchievementAlreadyDefined(definition.id));
+                }
+
+                let max_achievements = self.runtime.application_parameters().max_achievements;
+                let catalogue_size = *self.state.catalogue_size.get();
+                if catalogue_size >= max_achievements {
+                    return Err(HubError::CatalogueFull(max_achievements));
+                }
+
+                self.state.achievement_catalogue.insert(&definition.id, definition.clone())
+                    .expect("Failed to save achievement definition");
+                self.state.catalogue_size.set(catalogue_size + 1);
+
+                log::info!("Achievement {} added to the catalogue", definition.id);
+                Ok(())
+            }
+            HubOperation::ReportAchievement {
+                hub_chain_id,
                 player_id,
                 achievement_id,
-                chain_id,
-                timestamp,
                 metadata,
             } => {
-                // Create the achievement record
-                let player_achievement = PlayerAchievement {
-                    achievement_id: achievement_id.clone(),
-                    chain_id,
-                    timestamp,
-                    metadata: metadata.clone(),
-                };
-                
-                // Add to player's achievements
-                if let Some(mut achievements) = self.state.player_achievements.get_mut(&player_id).await.expect("Failed to get player achievements") {
-                    achievements.push(player_achievement);
-                } else {
-                    let mut achievements = Vec::new();
-                    achievements.push(player_achievement);
-                    if let Err(e) = self.state.player_achievements.insert(&player_id, achievements).await {
-                        linera_sdk::log::error!("Failed to save player achievements for player {}: {}", player_id, e);
-                        return;
-                    }
-                }
-                
-                // Add to all achievements by achievement_id
-                let achievement_record = hub_state::AchievementRecord {
-                    achievement_id: achievement_id.clone(),
+                // Runs on the world chain; the hub chain records the achievement when the message arrives,
+                // if the calling application is the game registered for this chain
+                let application_id = self.runtime.authenticated_caller_id()
+                    .ok_or(HubError::MissingCaller)?;
+                let message = HubMessage::AchievementSubmitted {
                     player_id: player_id.clone(),
-                    chain_id,
-                    timestamp,
+                    achievement_id: achievement_id.clone(),
+                    chain_id: self.runtime.chain_id(),
+                    application_id,
+                    timestamp: self.runtime.system_time().micros(),
                     metadata,
                 };
-                
-                if let Some(mut records) = self.state.all_achievements.get_mut(&achievement_id).await.expect("Failed to get achievement records") {
-                    records.push(achievement_record);
-                } else {
-                    let mut records = Vec::new();
-                    records.push(achievement_record);
-                    if let Err(e) = self.state.all_achievements.insert(&achievement_id, records).await {
-                        linera_sdk::log::error!("Failed to save achievement records for achievement {}: {}", achievement_id, e);
-                        return;
-                    }
-                }
-                
-                // Update counters
-                let current_achievements = *self.state.total_achievements.get();
-                self.state.total_achievements.set(current_achievements + 1);
-                
-                linera_sdk::log::info!("Achievement {} submitted for player {} from chain {}", achievement_id, player_id, chain_id);
+                self.runtime
+                    .prepare_message(message)
+                    .with_authentication()
+                    .send_to(hub_chain_id);
+
+                log::info!("Achievement {} reported for player {} to hub chain {}", achievement_id, player_id, hub_chain_id);
+                Ok(())
             }
-            HubOperation::RegisterWorldChain {
-                chain_id,
-                world_region,
+            HubOperation::ReportPlayerProgress {
+                hub_chain_id,
+                player_id,
+                level,
+                battle_wins_gained,
             } => {
-                // Register the world chain
-                let world_chain_info = WorldChainInfo {
-                    world_region: world_region.clone(),
-                    registration_timestamp: self.runtime.system_time().as_millis(),
-                    active: true,
+                // Runs on the world chain and is checked like achievement reports
+                let application_id = self.runtime.authenticated_caller_id()
+                    .ok_or(HubError::MissingCaller)?;
+                let message = HubMessage::PlayerProgressReported {
+                    player_id,
+                    chain_id: self.runtime.chain_id(),
+                    application_id,
+                    level,
+                    battle_wins_gained,
+                    timestamp: self.runtime.system_time().micros(),
                 };
-                
-                if let Err(e) = self.state.world_chains.insert(&chain_id, world_chain_info).await {
-                    linera_sdk::log::error!("Failed to register world chain {}: {}", chain_id, e);
-                    return;
+                self.runtime
+                    .prepare_message(message)
+                    .with_authentication()
+                    .send_to(hub_chain_id);
+                Ok(())
+            }
+            HubOperation::SendHeartbeat { hub_chain_id } => {
+                // Runs on the world chain; the hub chain takes the sender from the message origin
+                self.runtime
+                    .prepare_message(HubMessage::Heartbeat)
+                    .with_authentication()
+                    .send_to(hub_chain_id);
+                Ok(())
+            }
+            HubOperation::DeactivateWorldChain { chain_id } => {
+                self.check_admin().await?;
+                let Some(info) = self.state.world_chains.get_mut(&chain_id).await.expect("Failed to get world chain info") else {
+                    return Err(HubError::UnknownWorldChain(chain_id));
+                };
+                info.active = false;
+
+                log::info!("World chain {} deactivated", chain_id);
+                Ok(())
+            }
+            HubOperation::AddAdmin { owner } => {
+                self.check_admin().await?;
+                self.state.admins.insert(&owner).expect("Failed to add admin");
+
+                log::info!("Added hub admin {}", owner);
+                Ok(())
+            }
+            HubOperation::RemoveAdmin { owner } => {
+                self.check_admin().await?;
+                if !self.state.admins.contains(&owner).await.expect("Failed to get admin") {
+                    return Ok(());
+                }
+                if self.state.admins.count().await.expect("Failed to count admins") == 1 {
+                    return Err(HubError::LastAdmin);
                 }
-                
-                // Update counter
-                let current_chains = *self.state.total_chains.get();
-                self.state.total_chains.set(current_chains + 1);
-                
-                linera_sdk::log::info!("World chain {} registered with region {}", chain_id, world_region);
+                self.state.admins.remove(&owner).expect("Failed to remove admin");
+
+                log::info!("Removed hub admin {}", owner);
+                Ok(())
+            }
+            HubOperation::StartSeason { name } => {
+                self.check_admin().await?;
+                if let Some(season) = self.state.current_season.get() {
+                    return Err(HubError::SeasonInProgress(season.season_id));
+                }
+
+                let season_id = *self.state.next_season_id.get();
+                self.state.next_season_id.set(season_id + 1);
+                self.state.current_season.set(Some(SeasonInfo {
+                    season_id,
+                    name: name.clone(),
+                    started_at: self.runtime.system_time().micros(),
+                }));
+                // Points and wins earned between seasons do not count towards this one
+                self.state.start_scoring_period();
+
+                log::info!("Season {} ({}) started", season_id, name);
+                Ok(())
+            }
+            HubOperation::EndSeason => {
+                self.check_admin().await?;
+                let Some(season) = self.state.current_season.get().clone() else {
+                    return Err(HubError::NoActiveSeason);
+                };
+                let season_id = season.season_id;
+
+                let archive = SeasonArchive {
+                    season,
+                    ended_at: self.runtime.system_time().micros(),
+                    achievement_points: self.state.leaderboard(LeaderboardKind::AchievementPoints).await,
+                    level: self.state.leaderboard(LeaderboardKind::Level).await,
+                    battle_wins: self.state.leaderboard(LeaderboardKind::BattleWins).await,
+                };
+                self.state.season_archives.insert(&season_id, archive)
+                    .expect("Failed to archive season");
+                self.state.current_season.set(None);
+
+                // Points and wins are seasonal; the archive keeps the leaderboards' best players
+                self.state.start_scoring_period();
+
+                log::info!("Season {} ended", season_id);
+                Ok(())
             }
         }
     }
@@ -133,76 +226,66 @@ impl Contract for RpgHubContract {
                 player_id,
                 achievement_id,
                 chain_id,
+                application_id,
                 timestamp,
                 metadata,
             } => {
-                // This is essentially the same as the operation, just via cross-chain message
-                let player_achievement = PlayerAchievement {
-                    achievement_id: achievement_id.clone(),
-                    chain_id,
-                    timestamp,
-                    metadata: metadata.clone(),
-                };
-                
-                // Add to player's achievements
-                if let Some(mut achievements) = self.state.player_achievements.get_mut(&player_id).await.expect("Failed to get player achievements") {
-                    achievements.push(player_achievement);
-                } else {
-                    let mut achievements = Vec::new();
-                    achievements.push(player_achievement);
-                    if let Err(e) = self.state.player_achievements.insert(&player_id, achievements).await {
-                        linera_sdk::log::error!("Failed to save player achievements for player {}: {}", player_id, e);
-                        return;
-                    }
+                if let Err(e) = self.check_world_chain(chain_id, application_id).await {
+                    log::warn!("Ignored achievement {} for player {}: {}", achievement_id, player_id, e);
+                    return;
                 }
-                
-                // Add to all achievements by achievement_id
-                let achievement_record = hub_state::AchievementRecord {
-                    achievement_id: achievement_id.clone(),
-                    player_id: player_id.clone(),
-                    chain_id,
-                    timestamp,
-                    metadata,
-                };
-                
-                if let Some(mut records) = self.state.all_achievements.get_mut(&achievement_id).await.expect("Failed to get achievement records") {
-                    records.push(achievement_record);
-                } else {
-                    let mut records = Vec::new();
-                    records.push(achievement_record);
-                    if let Err(e) = self.state.all_achievements.insert(&achievement_id, records).await {
-                        linera_sdk::log::error!("Failed to save achievement records for achievement {}: {}", achievement_id, e);
-                        return;
-                    }
+                if let Err(e) = self.record_achievement(player_id.clone(), achievement_id.clone(), chain_id, timestamp, metadata).await {
+                    log::warn!("Ignored achievement {} for player {} from chain {}: {}", achievement_id, player_id, chain_id, e);
+                    return;
                 }
-                
-                // Update counters
-                let current_achievements = *self.state.total_achievements.get();
-                self.state.total_achievements.set(current_achievements + 1);
-                
-                linera_sdk::log::info!("Achievement {} submitted via message for player {} from chain {}", achievement_id, player_id, chain_id);
+
+                log::info!("Achievement {} submitted via message for player {} from chain {}", achievement_id, player_id, chain_id);
+            }
+            HubMessage::PlayerProgressReported {
+                player_id,
+                chain_id,
+                application_id,
+                level,
+                battle_wins_gained,
+                timestamp,
+            } => {
+                if let Err(e) = self.check_world_chain(chain_id, application_id).await {
+                    log::warn!("Ignored progress of player {}: {}", player_id, e);
+                    return;
+                }
+                let mut score = self.state.player_score(&player_id).await.unwrap_or_default();
+                score.level = level;
+                score.battle_wins = score.battle_wins.saturating_add(battle_wins_gained);
+                score.last_chain_id = Some(chain_id);
+                score.updated_at = timestamp;
+                self.state.set_score(&player_id, score).await;
+
+                log::info!("Progress of player {} reported from chain {}", player_id, chain_id);
             }
             HubMessage::WorldChainRegistered {
                 chain_id,
                 world_region,
+                game_application_id,
             } => {
-                // Register the world chain via cross-chain message
-                let world_chain_info = WorldChainInfo {
-                    world_region: world_region.clone(),
-                    registration_timestamp: self.runtime.system_time().as_millis(),
-                    active: true,
-                };
-                
-                if let Err(e) = self.state.world_chains.insert(&chain_id, world_chain_info).await {
-                    linera_sdk::log::error!("Failed to register world chain via message {}: {}", chain_id, e);
+                if let Err(e) = self.check_admin().await {
+                    log::warn!("Ignored registration of world chain {}: {}", chain_id, e);
                     return;
                 }
-                
-                // Update counter
-                let current_chains = *self.state.total_chains.get();
-                self.state.total_chains.set(current_chains + 1);
-                
-                linera_sdk::log::info!("World chain {} registered via message with region {}", chain_id, world_region);
+                self.register_world_chain(chain_id, world_region.clone(), game_application_id).await;
+
+                log::info!("World chain {} registered via message with region {}", chain_id, world_region);
+            }
+            HubMessage::Heartbeat => {
+                let Some(chain_id) = self.runtime.message_origin_chain_id() else {
+                    log::warn!("Ignored heartbeat without an origin chain");
+                    return;
+                };
+                let Some(info) = self.state.world_chains.get_mut(&chain_id).await.expect("Failed to get world chain info") else {
+                    log::warn!("Ignored heartbeat from unregistered chain {}", chain_id);
+                    return;
+                };
+                // The hub's own clock, so a world chain cannot keep itself listed with a future timestamp
+                info.last_heartbeat = self.runtime.system_time().micros();
             }
         }
     }
@@ -210,4 +293,133 @@ impl Contract for RpgHubContract {
     async fn store(mut self) {
         self.state.save().await.expect("Failed to save hub state");
     }
-}
\ No newline at end of file
+}
+
+impl RpgHubContract {
+    /// Registers a world chain, or refreshes and reactivates it if it was registered before.
+    async fn register_world_chain(&mut self, chain_id: ChainId, world_region: String, game_application_id: ApplicationId) {
+        let now = self.runtime.system_time().micros();
+        if let Some(info) = self.state.world_chains.get_mut(&chain_id).await.expect("Failed to get world chain info") {
+            info.world_region = world_region;
+            info.game_application_id = game_application_id;
+            info.active = true;
+            info.last_heartbeat = now;
+            return;
+        }
+
+        let world_chain_info = WorldChainInfo {
+            chain_id,
+            world_region,
+            game_application_id,
+            registration_timestamp: now,
+            last_heartbeat: now,
+            active: true,
+        };
+        self.state.world_chains.insert(&chain_id, world_chain_info)
+            .expect("Failed to register world chain");
+
+        let current_chains = *self.state.total_chains.get();
+        self.state.total_chains.set(current_chains + 1);
+    }
+
+    /// Hub administration is only accepted on the chain that created the hub, from a signer in the admin set.
+    async fn check_admin(&mut self) -> Result<(), HubError> {
+        if self.runtime.chain_id() != self.runtime.application_creator_chain_id() {
+            return Err(HubError::NotHubChain);
+        }
+        let signer = self.runtime.authenticated_signer()
+            .ok_or(HubError::MissingSigner)?;
+        if !self.state.admins.contains(&signer).await.expect("Failed to get admin") {
+            return Err(HubError::Unauthorized(signer));
+        }
+        Ok(())
+    }
+
+    /// Checks that the incoming report comes from `chain_id`, that it is an active registered world chain, and that
+    /// `application_id` is the game application registered for it.
+    async fn check_world_chain(&mut self, chain_id: ChainId, application_id: ApplicationId) -> Result<(), HubError> {
+        let origin = self.runtime.message_origin_chain_id();
+        if origin != Some(chain_id) {
+            return Err(HubError::OriginMismatch(chain_id));
+        }
+        let info = self.state.world_chains.get(&chain_id).await
+            .expect("Failed to get world chain info")
+            .ok_or(HubError::UnknownWorldChain(chain_id))?;
+        if !info.active {
+            return Err(HubError::InactiveWorldChain(chain_id));
+        }
+        if info.game_application_id != application_id {
+            return Err(HubError::ApplicationMismatch(chain_id));
+        }
+        Ok(())
+    }
+
+    /// Records that `player_id` earned a catalogued achievement, at most once per player.
+    async fn record_achievement(
+        &mut self,
+        player_id: String,
+        achievement_id: String,
+        chain_id: ChainId,
+        timestamp: u64,
+        metadata: String,
+    ) -> Result<(), HubError> {
+        let Some(definition) = self.state.achievement_catalogue.get(&achievement_id).await.expect("Failed to get achievement definition") else {
+            return Err(HubError::UnknownAchievement(achievement_id));
+        };
+
+        let lookup_key = format!("{}:{}", player_id, achievement_id);
+        if self.state.player_achievement_lookup.contains_key(&lookup_key).await.expect("Failed to get player achievement lookup") {
+            return Err(HubError::DuplicateAchievement { player_id, achievement_id });
+        }
+
+        // Add to player's achievements
+        let player_achievement = PlayerAchievement {
+            achievement_id: achievement_id.clone(),
+            chain_id,
+            timestamp,
+            metadata: metadata.clone(),
+        };
+        if let Some(achievements) = self.state.player_achievements.get_mut(&player_id).await.expect("Failed to get player achievements") {
+            achievements.push(player_achievement);
+        } else {
+            self.state.player_achievements.insert(&player_id, vec![player_achievement])
+                .expect("Failed to save player achievements");
+        }
+
+        // Add to all achievements by achievement_id
+        let achievement_record = AchievementRecord {
+            achievement_id: achievement_id.clone(),
+            player_id: player_id.clone(),
+            chain_id,
+            timestamp,
+            metadata,
+        };
+        let record_index = if let Some(records) = self.state.all_achievements.get_mut(&achievement_id).await.expect("Failed to get achievement records") {
+            records.push(achievement_record);
+            records.len() - 1
+        } else {
+            self.state.all_achievements.insert(&achievement_id, vec![achievement_record])
+                .expect("Failed to save achievement records");
+            0
+        };
+
+        // Remember where the record lives, which also marks the pair as earned
+        let record_id = format!("{}#{}", achievement_id, record_index);
+        self.state.player_achievement_lookup.insert(&lookup_key, vec![record_id])
+            .expect("Failed to save player achievement lookup");
+
+        // Credit the achievement's points on the leaderboard
+        let mut score = self.state.player_score(&player_id).await.unwrap_or_default();
+        score.achievement_points = score.achievement_points.saturating_add(definition.points);
+        score.updated_at = timestamp;
+        self.state.set_score(&player_id, score).await;
+
+        // Update counters
+        let current_achievements = *self.state.total_achievements.get();
+        self.state.total_achievements.set(current_achievements + 1);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod hub_test;
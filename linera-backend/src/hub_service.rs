@@ -3,13 +3,20 @@
 
 #![cfg_attr(target_arch = "wasm32", no_main)]
 
-mod hub_state;
-use std::sync::Arc;
+use std::{collections::BTreeSet, sync::Arc};
 
-use async_graphql::{EmptySubscription, Object, Request, Response, Schema};
-use linera_sdk::{linera_base_types::WithServiceAbi, views::View, Service, ServiceRuntime};
+use async_graphql::{EmptySubscription, InputObject, Object, Request, Response, Schema, SimpleObject};
+use linera_sdk::{
+    linera_base_types::{AccountOwner, ApplicationId, ChainId, WithServiceAbi},
+    views::View,
+    Service, ServiceRuntime,
+};
 
-use hub_state::{HubState, PlayerAchievement};
+use rpg_game::hub_abi::{AchievementDefinition, HubOperation, LeaderboardKind, DEFAULT_HEARTBEAT_TIMEOUT_MICROS};
+use rpg_game::hub_state::{
+    AchievementRecord, HubState, LeaderboardEntry, PlayerAchievement, PlayerScore, SeasonArchive, SeasonInfo,
+    SeasonPlacement, WorldChainInfo,
+};
 
 pub struct RpgHubService {
     state: Arc<HubState>,
@@ -19,7 +26,7 @@ pub struct RpgHubService {
 linera_sdk::service!(RpgHubService);
 
 impl WithServiceAbi for RpgHubService {
-    type Abi = crate::hub_abi::RpgHubAbi;
+    type Abi = rpg_game::hub_abi::RpgHubAbi;
 }
 
 impl Service for RpgHubService {
@@ -39,6 +46,7 @@ impl Service for RpgHubService {
         let schema = Schema::build(
             HubQueryRoot {
                 state: self.state.clone(),
+                runtime: self.runtime.clone(),
             },
             HubMutationRoot {
                 runtime: self.runtime.clone(),
@@ -50,8 +58,107 @@ impl Service for RpgHubService {
     }
 }
 
+/// Page size used when a list query does not ask for one
+const DEFAULT_PAGE_SIZE: u64 = 20;
+/// Largest page a list query returns
+const MAX_PAGE_SIZE: u64 = 100;
+
+#[derive(Default, InputObject)]
+struct WorldChainFilter {
+    world_region: Option<String>,
+    active: Option<bool>,
+    /// Only chains registered at or after this time, in microseconds
+    registered_after: Option<u64>,
+    /// Only chains registered at or before this time, in microseconds
+    registered_before: Option<u64>,
+}
+
+#[derive(Default, InputObject)]
+struct AchievementRecordFilter {
+    achievement_id: Option<String>,
+    player_id: Option<String>,
+    /// Only achievements earned on a chain of this region
+    world_region: Option<String>,
+    chain_id: Option<ChainId>,
+    /// Only achievements earned at or after this time, in microseconds
+    from_timestamp: Option<u64>,
+    /// Only achievements earned at or before this time, in microseconds
+    to_timestamp: Option<u64>,
+}
+
+#[derive(SimpleObject)]
+struct WorldChainPage {
+    items: Vec<WorldChainInfo>,
+    /// Pass as `after` to fetch the next page; `null` on the last page
+    next_cursor: Option<String>,
+}
+
+#[derive(SimpleObject)]
+struct AchievementRecordPage {
+    items: Vec<AchievementRecord>,
+    /// Pass as `after` to fetch the next page; `null` on the last page
+    next_cursor: Option<String>,
+}
+
+/// One page of a list query, filled while a map is walked in key order so that the walk can stop as soon as the
+/// page is full.
+struct Page<T> {
+    /// Cursor of the item the page starts after, until the walk reaches it
+    after: Option<String>,
+    first: usize,
+    items: Vec<(String, T)>,
+    has_more: bool,
+}
+
+impl<T> Page<T> {
+    fn new(after: Option<String>, first: Option<u64>) -> Self {
+        Page {
+            after,
+            first: first.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE) as usize,
+            items: Vec::new(),
+            has_more: false,
+        }
+    }
+
+    /// Returns whether the walk is past the cursor, so that the item with cursor `key` may go on the page.
+    fn is_past_cursor(&mut self, key: &str) -> bool {
+        match &self.after {
+            Some(cursor) if cursor == key => {
+                self.after = None;
+                false
+            }
+            Some(_) => false,
+            None => true,
+        }
+    }
+
+    /// Adds an item to the page, or returns `false` once the page is full and the walk can stop.
+    fn push(&mut self, key: String, item: T) -> bool {
+        if self.items.len() == self.first {
+            self.has_more = true;
+            return false;
+        }
+        self.items.push((key, item));
+        true
+    }
+
+    /// Returns the items and the cursor of the last one if more remain.
+    fn finish(self) -> async_graphql::Result<(Vec<T>, Option<String>)> {
+        if let Some(cursor) = self.after {
+            return Err(async_graphql::Error::new(format!("unknown cursor {}", cursor)));
+        }
+        let next_cursor = if self.has_more {
+            self.items.last().map(|(key, _)| key.clone())
+        } else {
+            None
+        };
+        Ok((self.items.into_iter().map(|(_, item)| item).collect(), next_cursor))
+    }
+}
+
 struct HubQueryRoot {
     state: Arc<HubState>,
+    runtime: Arc<ServiceRuntime<RpgHubService>>,
 }
 
 #[Object]
@@ -61,6 +168,12 @@ impl HubQueryRoot {
             .expect("Failed to get player achievements")
     }
 
+    /// Owners allowed to administer the hub
+    async fn admins(&self) -> Vec<AccountOwner> {
+        self.state.admins.indices().await
+            .expect("Failed to list admins")
+    }
+
     async fn world_chains_count(&self) -> u64 {
         *self.state.total_chains.get()
     }
@@ -69,18 +182,227 @@ impl HubQueryRoot {
         *self.state.total_achievements.get()
     }
 
-    async fn world_chain_info(&self, chain_id: String) -> Option<hub_state::WorldChainInfo> {
-        use linera_sdk::linera_base_types::ChainId;
-        // Parse the chain ID string to ChainId (this would need proper parsing in real implementation)
-        // For now, we'll assume it's used differently
-        // This is a simplified version - would need proper ChainId deserialization
-        None  // Placeholder - would need to implement proper ChainId parsing
+    async fn world_chain_info(&self, chain_id: ChainId) -> Option<WorldChainInfo> {
+        self.state.world_chains.get(&chain_id).await
+            .expect("Failed to get world chain info")
+    }
+
+    /// Registered world chains matching `filter`, `first` at a time after the `after` cursor
+    async fn world_chains_page(
+        &self,
+        filter: Option<WorldChainFilter>,
+        after: Option<String>,
+        first: Option<u64>,
+    ) -> async_graphql::Result<WorldChainPage> {
+        let filter = filter.unwrap_or_default();
+        let mut page = Page::new(after, first);
+        self.state.world_chains
+            .for_each_index_value_while(|chain_id, info| {
+                let key = chain_id.to_string();
+                if !page.is_past_cursor(&key) {
+                    return Ok(true);
+                }
+                let matches = filter.world_region.as_ref().is_none_or(|region| info.world_region == *region)
+                    && filter.active.is_none_or(|active| info.active == active)
+                    && filter.registered_after.is_none_or(|after| info.registration_timestamp >= after)
+                    && filter.registered_before.is_none_or(|before| info.registration_timestamp <= before);
+                Ok(!matches || page.push(key, info.into_owned()))
+            })
+            .await
+            .expect("Failed to list world chains");
+
+        let (items, next_cursor) = page.finish()?;
+        Ok(WorldChainPage { items, next_cursor })
+    }
+
+    /// Achievement records across the hub matching `filter`, `first` at a time after the `after` cursor
+    async fn achievement_records_page(
+        &self,
+        filter: Option<AchievementRecordFilter>,
+        after: Option<String>,
+        first: Option<u64>,
+    ) -> async_graphql::Result<AchievementRecordPage> {
+        let filter = filter.unwrap_or_default();
+        let region_chains = match &filter.world_region {
+            Some(region) => Some(self.chains_in_region(region).await),
+            None => None,
+        };
+
+        // Records keep their "achievement_id#index" ID, so cursors stay valid as new records arrive
+        let mut page = Page::new(after, first);
+        let cursor_achievement_id = page.after.as_ref()
+            .and_then(|cursor| cursor.rsplit_once('#'))
+            .map(|(achievement_id, _)| achievement_id.to_string());
+        self.state.all_achievements
+            .for_each_index_value_while(|achievement_id, records| {
+                if filter.achievement_id.as_ref().is_some_and(|id| *id != achievement_id) {
+                    return Ok(true);
+                }
+                // Achievements before the cursor's are skipped whole
+                if page.after.is_some() && cursor_achievement_id.as_ref() != Some(&achievement_id) {
+                    return Ok(true);
+                }
+                for (index, record) in records.iter().enumerate() {
+                    let key = format!("{}#{}", achievement_id, index);
+                    if !page.is_past_cursor(&key) {
+                        continue;
+                    }
+                    let matches = filter.player_id.as_ref().is_none_or(|player_id| record.player_id == *player_id)
+                        && filter.chain_id.is_none_or(|chain_id| record.chain_id == chain_id)
+                        && region_chains.as_ref().is_none_or(|chains| chains.contains(&record.chain_id))
+                        && filter.from_timestamp.is_none_or(|from| record.timestamp >= from)
+                        && filter.to_timestamp.is_none_or(|to| record.timestamp <= to);
+                    if matches && !page.push(key, record.clone()) {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            })
+            .await
+            .expect("Failed to list achievement records");
+
+        let (items, next_cursor) = page.finish()?;
+        Ok(AchievementRecordPage { items, next_cursor })
+    }
+
+    /// Active world chains that sent a heartbeat within `timeout_micros` (10 minutes by default)
+    async fn active_world_chains(&self, timeout_micros: Option<u64>) -> Vec<WorldChainInfo> {
+        let cutoff = self.heartbeat_cutoff(timeout_micros);
+        self.world_chains_where(|info| info.active && info.last_heartbeat >= cutoff).await
+    }
+
+    /// Active world chains that have not sent a heartbeat within `timeout_micros` (10 minutes by default)
+    async fn stale_world_chains(&self, timeout_micros: Option<u64>) -> Vec<WorldChainInfo> {
+        let cutoff = self.heartbeat_cutoff(timeout_micros);
+        self.world_chains_where(|info| info.active && info.last_heartbeat < cutoff).await
+    }
+
+    /// World chains that were deactivated
+    async fn inactive_world_chains(&self) -> Vec<WorldChainInfo> {
+        self.world_chains_where(|info| !info.active).await
+    }
+
+    async fn achievement_definition(&self, achievement_id: String) -> Option<AchievementDefinition> {
+        self.state.achievement_catalogue.get(&achievement_id).await
+            .expect("Failed to get achievement definition")
+    }
+
+    /// Every achievement players can earn
+    async fn achievement_catalogue(&self) -> Vec<AchievementDefinition> {
+        let mut definitions = Vec::new();
+        self.state.achievement_catalogue
+            .for_each_index_value(|_achievement_id, definition| {
+                definitions.push(definition.into_owned());
+                Ok(())
+            })
+            .await
+            .expect("Failed to list achievement catalogue");
+        definitions
+    }
+
+    async fn has_achievement(&self, player_id: String, achievement_id: String) -> bool {
+        let lookup_key = format!("{}:{}", player_id, achievement_id);
+        self.state.player_achievement_lookup.contains_key(&lookup_key).await
+            .expect("Failed to get player achievement lookup")
     }
 
-    async fn achievement_records(&self, achievement_id: String) -> Option<Vec<hub_state::AchievementRecord>> {
+    async fn achievement_records(&self, achievement_id: String) -> Option<Vec<AchievementRecord>> {
         self.state.all_achievements.get(&achievement_id).await
             .expect("Failed to get achievement records")
     }
+
+    /// The best players across every region, ranked by `kind`; at most `MAX_PAGE_SIZE` at a time
+    async fn leaderboard(&self, kind: LeaderboardKind, offset: Option<u64>, limit: Option<u64>) -> Vec<LeaderboardEntry> {
+        let offset = offset.unwrap_or(0) as usize;
+        let limit = limit.unwrap_or(10).min(MAX_PAGE_SIZE) as usize;
+        self.state.leaderboard(kind).await
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .collect()
+    }
+
+    /// Position of `player_id` on the `kind` leaderboard, if they are among the `LEADERBOARD_SIZE` best
+    async fn player_rank(&self, kind: LeaderboardKind, player_id: String) -> Option<LeaderboardEntry> {
+        self.state.leaderboard(kind).await
+            .into_iter()
+            .find(|entry| entry.player_id == player_id)
+    }
+
+    async fn player_score(&self, player_id: String) -> Option<PlayerScore> {
+        self.state.player_score(&player_id).await
+    }
+
+    async fn current_season(&self) -> Option<SeasonInfo> {
+        self.state.current_season.get().clone()
+    }
+
+    /// Every finished season, oldest first
+    async fn seasons(&self) -> Vec<SeasonArchive> {
+        let mut archives = Vec::new();
+        self.state.season_archives
+            .for_each_index_value(|_season_id, archive| {
+                archives.push(archive.into_owned());
+                Ok(())
+            })
+            .await
+            .expect("Failed to list season archives");
+        archives
+    }
+
+    async fn season(&self, season_id: u64) -> Option<SeasonArchive> {
+        self.state.season_archives.get(&season_id).await
+            .expect("Failed to get season archive")
+    }
+
+    /// Where `player_id` finished on the `kind` leaderboard of each past season they were ranked in
+    async fn player_season_placements(&self, kind: LeaderboardKind, player_id: String) -> Vec<SeasonPlacement> {
+        let mut placements = Vec::new();
+        self.state.season_archives
+            .for_each_index_value(|season_id, archive| {
+                if let Some(entry) = archive.leaderboard(kind).iter().find(|entry| entry.player_id == player_id) {
+                    placements.push(SeasonPlacement {
+                        season_id,
+                        season_name: archive.season.name.clone(),
+                        rank: entry.rank,
+                        score: entry.score,
+                    });
+                }
+                Ok(())
+            })
+            .await
+            .expect("Failed to list season archives");
+        placements
+    }
+}
+
+impl HubQueryRoot {
+    fn heartbeat_cutoff(&self, timeout_micros: Option<u64>) -> u64 {
+        let timeout = timeout_micros.unwrap_or(DEFAULT_HEARTBEAT_TIMEOUT_MICROS);
+        self.runtime.system_time().micros().saturating_sub(timeout)
+    }
+
+    async fn chains_in_region(&self, world_region: &str) -> BTreeSet<ChainId> {
+        self.world_chains_where(|info| info.world_region == world_region).await
+            .into_iter()
+            .map(|info| info.chain_id)
+            .collect()
+    }
+
+    /// Returns the registered world chains for which `keep` holds.
+    async fn world_chains_where(&self, keep: impl Fn(&WorldChainInfo) -> bool + Sync) -> Vec<WorldChainInfo> {
+        let mut chains = Vec::new();
+        self.state.world_chains
+            .for_each_index_value(|_chain_id, info| {
+                if keep(&info) {
+                    chains.push(info.into_owned());
+                }
+                Ok(())
+            })
+            .await
+            .expect("Failed to list world chains");
+        chains
+    }
 }
 
 struct HubMutationRoot {
@@ -89,39 +411,62 @@ struct HubMutationRoot {
 
 #[Object]
 impl HubMutationRoot {
-    async fn submit_achievement(
-        &self,
-        player_id: String,
-        achievement_id: String,
-        chain_id_str: String,  // Would need to convert to ChainId
-        timestamp: u64,
-        metadata: String,
-    ) -> [u8; 0] {
-        // This would need proper ChainId conversion
-        // For the example, we'll use a placeholder
-        // In a real implementation, we'd properly parse the chain_id_str
-        let operation = crate::hub_abi::HubOperation::SubmitAchievement {
-            player_id,
-            achievement_id,
-            chain_id: self.runtime.chain_id(),  // Using current chain as placeholder
-            timestamp,
-            metadata,
-        };
+    async fn add_admin(&self, owner: AccountOwner) -> [u8; 0] {
+        let operation = HubOperation::AddAdmin { owner };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn remove_admin(&self, owner: AccountOwner) -> [u8; 0] {
+        let operation = HubOperation::RemoveAdmin { owner };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn register_achievement(&self, definition: AchievementDefinition) -> [u8; 0] {
+        let operation = HubOperation::RegisterAchievement { definition };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn start_season(&self, name: String) -> [u8; 0] {
+        let operation = HubOperation::StartSeason { name };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn end_season(&self) -> [u8; 0] {
+        self.runtime.schedule_operation(&HubOperation::EndSeason);
+        []
+    }
+
+    async fn deactivate_world_chain(&self, chain_id: ChainId) -> [u8; 0] {
+        let operation = HubOperation::DeactivateWorldChain { chain_id };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn send_heartbeat(&self, hub_chain_id: ChainId) -> [u8; 0] {
+        let operation = HubOperation::SendHeartbeat { hub_chain_id };
         self.runtime.schedule_operation(&operation);
         []
     }
 
     async fn register_world_chain(
         &self,
-        chain_id_str: String,  // Would need to convert to ChainId
+        chain_id: ChainId,
         world_region: String,
+        game_application_id: ApplicationId,
     ) -> [u8; 0] {
-        // This would need proper ChainId conversion
-        let operation = crate::hub_abi::HubOperation::RegisterWorldChain {
-            chain_id: self.runtime.chain_id(),  // Using current chain as placeholder
+        let operation = HubOperation::RegisterWorldChain {
+            chain_id,
             world_region,
+            game_application_id,
         };
         self.runtime.schedule_operation(&operation);
         []
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod hub_service_test;
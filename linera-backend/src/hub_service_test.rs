@@ -0,0 +1,197 @@
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, sync::Arc};
+
+    use async_graphql::Request;
+    use linera_sdk::{
+        linera_base_types::{ApplicationId, ChainId, CryptoHash},
+        views::View,
+        Service, ServiceRuntime,
+    };
+    use rpg_game::{
+        hub_abi::{LeaderboardKind, LEADERBOARD_SIZE},
+        hub_state::{AchievementRecord, HubState, PlayerScore, WorldChainInfo},
+    };
+    use serde_json::Value;
+
+    use crate::RpgHubService;
+
+    fn chain(index: u32) -> ChainId {
+        ChainId(CryptoHash::test_hash(format!("chain{}", index)))
+    }
+
+    fn world_chain(index: u32, world_region: &str, active: bool) -> WorldChainInfo {
+        WorldChainInfo {
+            chain_id: chain(index),
+            world_region: world_region.to_string(),
+            game_application_id: ApplicationId::new(CryptoHash::test_hash("game")),
+            registration_timestamp: u64::from(index) * 10,
+            last_heartbeat: u64::from(index) * 10,
+            active,
+        }
+    }
+
+    fn record(achievement_id: &str, player_id: &str) -> AchievementRecord {
+        AchievementRecord {
+            achievement_id: achievement_id.to_string(),
+            player_id: player_id.to_string(),
+            chain_id: chain(0),
+            timestamp: 0,
+            metadata: "{}".to_string(),
+        }
+    }
+
+    /// Creates a hub service over a state that `setup` fills in.
+    async fn service(setup: impl AsyncFnOnce(&mut HubState)) -> RpgHubService {
+        let runtime = ServiceRuntime::<RpgHubService>::new();
+        let mut state = HubState::load(runtime.root_view_storage_context())
+            .await
+            .expect("Failed to load state");
+        setup(&mut state).await;
+        RpgHubService {
+            state: Arc::new(state),
+            runtime: Arc::new(runtime),
+        }
+    }
+
+    async fn query(service: &RpgHubService, query: &str) -> Result<Value, String> {
+        let response = service.handle_query(Request::new(query)).await;
+        if let Some(error) = response.errors.first() {
+            return Err(error.message.clone());
+        }
+        Ok(response.data.into_json().unwrap())
+    }
+
+    /// Follows `nextCursor` through every page of `field`, returning the `key` of each item and the number of pages.
+    async fn walk(service: &RpgHubService, field: &str, arguments: &str, key: &str) -> (Vec<String>, usize) {
+        let mut keys = Vec::new();
+        let mut after = String::new();
+        for pages in 1.. {
+            let data = query(service, &format!(
+                "{{ {}({} first: 2 {}) {{ items {{ {} }} nextCursor }} }}", field, arguments, after, key,
+            )).await.unwrap();
+            let page = &data[field];
+            keys.extend(page["items"].as_array().unwrap().iter().map(|item| item[key].as_str().unwrap().to_string()));
+            match page["nextCursor"].as_str() {
+                Some(cursor) => after = format!("after: \"{}\"", cursor),
+                None => return (keys, pages),
+            }
+        }
+        unreachable!()
+    }
+
+    #[tokio::test]
+    async fn test_world_chains_page() {
+        let service = service(async |state| {
+            for (index, world_region, active) in [(1, "north", true), (2, "south", true), (3, "north", false), (4, "north", true), (5, "east", true)] {
+                state.world_chains.insert(&chain(index), world_chain(index, world_region, active)).unwrap();
+            }
+        }).await;
+
+        // Every chain shows up exactly once across the pages
+        let (chains, pages) = walk(&service, "worldChainsPage", "", "chainId").await;
+        assert_eq!(pages, 3);
+        assert_eq!(chains.len(), 5);
+        assert_eq!(
+            chains.into_iter().collect::<BTreeSet<_>>(),
+            (1..=5).map(|index| chain(index).to_string()).collect::<BTreeSet<_>>(),
+        );
+
+        // Filtered out chains do not take room on a page
+        let (chains, pages) = walk(&service, "worldChainsPage", "filter: { worldRegion: \"north\", active: true }", "chainId").await;
+        assert_eq!(pages, 1);
+        assert_eq!(
+            chains.into_iter().collect::<BTreeSet<_>>(),
+            [1, 4].map(|index| chain(index).to_string()).into_iter().collect::<BTreeSet<_>>(),
+        );
+
+        let error = query(&service, "{ worldChainsPage(after: \"nowhere\") { nextCursor } }").await.unwrap_err();
+        assert_eq!(error, "unknown cursor nowhere");
+    }
+
+    #[tokio::test]
+    async fn test_world_chain_info() {
+        let service = service(async |state| {
+            state.world_chains.insert(&chain(1), world_chain(1, "north", true)).unwrap();
+        }).await;
+
+        let data = query(&service, &format!(
+            "{{ worldChainInfo(chainId: \"{}\") {{ worldRegion active registrationTimestamp }} }}", chain(1),
+        )).await.unwrap();
+        assert_eq!(data["worldChainInfo"]["worldRegion"], "north");
+        assert_eq!(data["worldChainInfo"]["active"], true);
+        assert_eq!(data["worldChainInfo"]["registrationTimestamp"], 10);
+
+        let data = query(&service, &format!(
+            "{{ worldChainInfo(chainId: \"{}\") {{ worldRegion }} }}", chain(2),
+        )).await.unwrap();
+        assert!(data["worldChainInfo"].is_null());
+
+        // Chain IDs that do not parse are refused before the state is read
+        for chain_id in ["not-a-chain", "", "abc123"] {
+            let query_text = format!("{{ worldChainInfo(chainId: \"{}\") {{ worldRegion }} }}", chain_id);
+            assert!(query(&service, &query_text).await.is_err(), "{} was accepted", chain_id);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_achievement_records_page() {
+        let service = service(async |state| {
+            state.all_achievements.insert(
+                &"first_blood".to_string(),
+                vec![record("first_blood", "hero"), record("first_blood", "mage"), record("first_blood", "knight")],
+            ).unwrap();
+            state.all_achievements.insert(
+                &"dragon_slayer".to_string(),
+                vec![record("dragon_slayer", "hero"), record("dragon_slayer", "archer")],
+            ).unwrap();
+        }).await;
+
+        let data = query(&service, "{ achievementRecordsPage(first: 2) { items { playerId } nextCursor } }").await.unwrap();
+        let cursor = data["achievementRecordsPage"]["nextCursor"].as_str().unwrap();
+        assert!(cursor.ends_with("#1"));
+
+        // Pages continue from the record after the cursor, across achievements
+        let (mut players, pages) = walk(&service, "achievementRecordsPage", "", "playerId").await;
+        assert_eq!(pages, 3);
+        players.sort();
+        assert_eq!(players, ["archer", "hero", "hero", "knight", "mage"]);
+
+        let (players, _) = walk(&service, "achievementRecordsPage", "filter: { playerId: \"hero\" }", "playerId").await;
+        assert_eq!(players, ["hero", "hero"]);
+
+        let error = query(&service, "{ achievementRecordsPage(after: \"first_blood#7\") { nextCursor } }").await.unwrap_err();
+        assert_eq!(error, "unknown cursor first_blood#7");
+    }
+
+    #[tokio::test]
+    async fn test_leaderboard_keeps_the_best_players() {
+        let service = service(async |state| {
+            for points in 0..150 {
+                let score = PlayerScore {
+                    achievement_points: points,
+                    ..PlayerScore::default()
+                };
+                state.set_score(&format!("player{:03}", points), score).await;
+            }
+        }).await;
+
+        let leaderboard = service.state.leaderboard(LeaderboardKind::AchievementPoints).await;
+        assert_eq!(leaderboard.len(), LEADERBOARD_SIZE as usize);
+        assert_eq!((leaderboard[0].rank, leaderboard[0].player_id.as_str(), leaderboard[0].score), (1, "player149", 149));
+        assert_eq!(leaderboard[99].score, 50);
+
+        // Ties on the other leaderboards are ordered by player ID
+        let level = service.state.leaderboard(LeaderboardKind::Level).await;
+        assert_eq!(level[0].player_id, "player000");
+
+        // A query cannot ask for more than a page
+        let data = query(&service, "{ leaderboard(kind: ACHIEVEMENT_POINTS, limit: 1000) { rank } }").await.unwrap();
+        assert_eq!(data["leaderboard"].as_array().unwrap().len(), 100);
+
+        let data = query(&service, "{ best: playerRank(kind: ACHIEVEMENT_POINTS, playerId: \"player140\") { rank } \
+            left_out: playerRank(kind: ACHIEVEMENT_POINTS, playerId: \"player010\") { rank } }").await.unwrap();
+        assert_eq!(data["best"]["rank"], 10);
+        assert!(data["left_out"].is_null());
+    }
+}
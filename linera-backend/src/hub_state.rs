@@ -1,8 +1,12 @@
-use linera_sdk::linera_base_types::ChainId;
-use linera_views::views::{MapView, RegisterView, RootView, ViewStorageContext};
+// RPG Hub State
+// SPDX-License-Identifier: MIT
+
+use linera_sdk::linera_base_types::{AccountOwner, ApplicationId, ChainId};
+use crate::hub_abi::{AchievementDefinition, LeaderboardKind, LEADERBOARD_SIZE};
+use linera_sdk::views::{MapView, RegisterView, RootView, SetView, ViewStorageContext};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, async_graphql::SimpleObject)]
 pub struct PlayerAchievement {
     pub achievement_id: String,
     pub chain_id: ChainId,
@@ -10,14 +14,21 @@ pub struct PlayerAchievement {
     pub metadata: String,  // JSON string with achievement details
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, async_graphql::SimpleObject)]
 pub struct WorldChainInfo {
+    pub chain_id: ChainId,
     pub world_region: String,
+    /// Game application whose reports the hub accepts from this chain
+    pub game_application_id: ApplicationId,
     pub registration_timestamp: u64,
+    /// Time of the last heartbeat, or of the registration if none arrived since
+    #[serde(default)]
+    pub last_heartbeat: u64,
+    /// Whether the region is in service; deactivated chains stay listed until registered again
     pub active: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, async_graphql::SimpleObject)]
 pub struct AchievementRecord {
     pub achievement_id: String,
     pub player_id: String,
@@ -26,6 +37,88 @@ pub struct AchievementRecord {
     pub metadata: String,
 }
 
+/// A player's standing on the hub's leaderboards
+#[derive(Debug, Clone, Default, Serialize, Deserialize, async_graphql::SimpleObject)]
+pub struct PlayerScore {
+    pub achievement_points: u64,
+    /// Level last reported by a world chain
+    pub level: u64,
+    pub battle_wins: u64,
+    /// World chain that last reported progress for the player
+    pub last_chain_id: Option<ChainId>,
+    pub updated_at: u64,
+    /// Scoring period the achievement points and battle wins were earned in
+    #[serde(default)]
+    pub period: u64,
+}
+
+impl PlayerScore {
+    /// Returns this score with the achievement points and battle wins of a scoring period other than `period` cleared.
+    pub fn in_period(mut self, period: u64) -> Self {
+        if self.period != period {
+            self.achievement_points = 0;
+            self.battle_wins = 0;
+            self.period = period;
+        }
+        self
+    }
+
+    /// Returns the value the player is ranked by on `kind` leaderboards.
+    pub fn score(&self, kind: LeaderboardKind) -> u64 {
+        match kind {
+            LeaderboardKind::AchievementPoints => self.achievement_points,
+            LeaderboardKind::Level => self.level,
+            LeaderboardKind::BattleWins => self.battle_wins,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, async_graphql::SimpleObject)]
+pub struct LeaderboardEntry {
+    /// 1-based position; ties are ordered by player ID
+    pub rank: u64,
+    pub player_id: String,
+    pub score: u64,
+}
+
+/// A season as it was opened by the hub
+#[derive(Debug, Clone, Serialize, Deserialize, async_graphql::SimpleObject)]
+pub struct SeasonInfo {
+    pub season_id: u64,
+    pub name: String,
+    pub started_at: u64,
+}
+
+/// The frozen leaderboards of a finished season
+#[derive(Debug, Clone, Serialize, Deserialize, async_graphql::SimpleObject)]
+pub struct SeasonArchive {
+    pub season: SeasonInfo,
+    pub ended_at: u64,
+    pub achievement_points: Vec<LeaderboardEntry>,
+    pub level: Vec<LeaderboardEntry>,
+    pub battle_wins: Vec<LeaderboardEntry>,
+}
+
+impl SeasonArchive {
+    /// Returns the archived leaderboard for `kind`.
+    pub fn leaderboard(&self, kind: LeaderboardKind) -> &[LeaderboardEntry] {
+        match kind {
+            LeaderboardKind::AchievementPoints => &self.achievement_points,
+            LeaderboardKind::Level => &self.level,
+            LeaderboardKind::BattleWins => &self.battle_wins,
+        }
+    }
+}
+
+/// Where a player finished on one leaderboard of a past season
+#[derive(Debug, Clone, Serialize, Deserialize, async_graphql::SimpleObject)]
+pub struct SeasonPlacement {
+    pub season_id: u64,
+    pub season_name: String,
+    pub rank: u64,
+    pub score: u64,
+}
+
 /// The hub application state.
 #[derive(RootView, async_graphql::SimpleObject)]
 #[view(context = ViewStorageContext)]
@@ -38,8 +131,123 @@ pub struct HubState {
     pub all_achievements: MapView<String, Vec<AchievementRecord>>,  // achievement_id -> records
     /// Player achievement lookup by achievement_id
     pub player_achievement_lookup: MapView<String, Vec<String>>,  // "player_id:achievement_id" -> [record_ids]
+    /// Achievements players can earn
+    pub achievement_catalogue: MapView<String, AchievementDefinition>,
+    /// Number of definitions in the catalogue
+    pub catalogue_size: RegisterView<u64>,
+    /// Leaderboard scores by player ID
+    pub player_scores: MapView<String, PlayerScore>,
+    /// The best `LEADERBOARD_SIZE` players of each leaderboard, in rank order
+    pub leaderboards: MapView<LeaderboardKind, Vec<LeaderboardEntry>>,
+    /// Season currently running, if any
+    pub current_season: RegisterView<Option<SeasonInfo>>,
+    /// Finished seasons by season ID
+    pub season_archives: MapView<u64, SeasonArchive>,
+    /// ID the next season will get
+    pub next_season_id: RegisterView<u64>,
+    /// Bumped whenever a season starts or ends; points and wins from an earlier period count as zero
+    pub scoring_period: RegisterView<u64>,
+    /// Owners allowed to register world chains and manage the catalogue and seasons
+    pub admins: SetView<AccountOwner>,
     /// Total registered chains count
     pub total_chains: RegisterView<u64>,
     /// Total achievements count
     pub total_achievements: RegisterView<u64>,
-}
\ No newline at end of file
+}
+
+impl HubState {
+    /// Returns the score of `player_id`, with the points and wins of an earlier scoring period cleared.
+    pub async fn player_score(&self, player_id: &str) -> Option<PlayerScore> {
+        let score = self.player_scores.get(player_id).await
+            .expect("Failed to get player score")?;
+        Some(score.in_period(*self.scoring_period.get()))
+    }
+
+    /// Starts a new scoring period, so that every player's points and wins start over without visiting their scores.
+    /// Levels carry over with the characters.
+    pub fn start_scoring_period(&mut self) {
+        let period = *self.scoring_period.get();
+        self.scoring_period.set(period + 1);
+        for kind in [LeaderboardKind::AchievementPoints, LeaderboardKind::BattleWins] {
+            self.leaderboards.remove(&kind)
+                .expect("Failed to reset leaderboard");
+        }
+    }
+
+    /// Returns the best players on `kind`, from best to worst.
+    pub async fn leaderboard(&self, kind: LeaderboardKind) -> Vec<LeaderboardEntry> {
+        self.leaderboards.get(&kind).await
+            .expect("Failed to get leaderboard")
+            .unwrap_or_default()
+    }
+
+    /// Saves the score of `player_id` and moves the player to their new place on every leaderboard.
+    ///
+    /// Levels can drop, and a player dropping on a full leaderboard may fall behind one left out of it, so that
+    /// leaderboard is rebuilt from every player's score.
+    pub async fn set_score(&mut self, player_id: &str, score: PlayerScore) {
+        let previous = self.player_score(player_id).await.unwrap_or_default();
+        self.player_scores.insert(player_id, score.clone())
+            .expect("Failed to save player score");
+
+        for kind in LeaderboardKind::ALL {
+            let entries = self.leaderboard(kind).await;
+            let dropped = score.score(kind) < previous.score(kind);
+            if dropped && entries.len() == LEADERBOARD_SIZE as usize && entries.iter().any(|entry| entry.player_id == player_id) {
+                self.rebuild(kind).await;
+            } else {
+                self.rank(kind, entries, player_id, score.score(kind));
+            }
+        }
+    }
+
+    /// Places `player_id` on the `kind` leaderboard `entries` with `score`, if it is among the best.
+    ///
+    /// The player is moved without looking at players left out, which is only correct when no one can overtake them.
+    fn rank(&mut self, kind: LeaderboardKind, mut entries: Vec<LeaderboardEntry>, player_id: &str, score: u64) {
+        entries.retain(|entry| entry.player_id != player_id);
+
+        // Ties are ordered by player ID
+        let position = entries
+            .iter()
+            .position(|entry| score > entry.score || (score == entry.score && player_id < entry.player_id.as_str()))
+            .unwrap_or(entries.len());
+        if position < LEADERBOARD_SIZE as usize {
+            entries.insert(position, LeaderboardEntry {
+                rank: 0,
+                player_id: player_id.to_string(),
+                score,
+            });
+            entries.truncate(LEADERBOARD_SIZE as usize);
+        }
+        self.save_leaderboard(kind, entries);
+    }
+
+    /// Recomputes the `kind` leaderboard from the scores of every player.
+    async fn rebuild(&mut self, kind: LeaderboardKind) {
+        let period = *self.scoring_period.get();
+        let mut entries = Vec::new();
+        self.player_scores.for_each_index_value(|player_id, score| {
+            entries.push(LeaderboardEntry {
+                rank: 0,
+                player_id,
+                score: score.into_owned().in_period(period).score(kind),
+            });
+            Ok(())
+        }).await.expect("Failed to get player scores");
+
+        // Ties are ordered by player ID
+        entries.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.player_id.cmp(&b.player_id)));
+        entries.truncate(LEADERBOARD_SIZE as usize);
+        self.save_leaderboard(kind, entries);
+    }
+
+    /// Numbers `entries`, already in rank order, and saves them as the `kind` leaderboard.
+    fn save_leaderboard(&mut self, kind: LeaderboardKind, mut entries: Vec<LeaderboardEntry>) {
+        for (index, entry) in entries.iter_mut().enumerate() {
+            entry.rank = index as u64 + 1;
+        }
+        self.leaderboards.insert(&kind, entries)
+            .expect("Failed to save leaderboard");
+    }
+}
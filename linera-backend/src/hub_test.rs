@@ -0,0 +1,547 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use linera_sdk::{
+        linera_base_types::{AccountOwner, ApplicationId, ChainId, CryptoHash, Timestamp},
+        views::{RootView, View},
+        Contract, ContractRuntime,
+    };
+    use rpg_game::{
+        hub_abi::{
+            AchievementDefinition, AchievementRarity, HubError, HubMessage, HubOperation, HubParameters, LeaderboardKind,
+            LEADERBOARD_SIZE,
+        },
+        hub_state::HubState,
+    };
+
+    use crate::RpgHubContract;
+
+    /// Signer of every block and message in these tests, unless a test says otherwise.
+    const OWNER: AccountOwner = AccountOwner::Address20([1; 20]);
+
+    fn chain(index: u32) -> ChainId {
+        ChainId(CryptoHash::test_hash(format!("chain{}", index)))
+    }
+
+    /// The game application running on a world chain.
+    fn game(name: &str) -> ApplicationId {
+        ApplicationId::new(CryptoHash::test_hash(name))
+    }
+
+    fn definition(id: &str, points: u64) -> AchievementDefinition {
+        AchievementDefinition {
+            id: id.to_string(),
+            title: id.replace('_', " "),
+            points,
+            rarity: AchievementRarity::Common,
+        }
+    }
+
+    fn register(chain_id: ChainId, world_region: &str, game: ApplicationId) -> HubOperation {
+        HubOperation::RegisterWorldChain {
+            chain_id,
+            world_region: world_region.to_string(),
+            game_application_id: game,
+        }
+    }
+
+    /// A message sent to another chain and not executed yet.
+    struct Delivery {
+        origin: ChainId,
+        signer: Option<AccountOwner>,
+        message: HubMessage,
+    }
+
+    /// The hub application on several chains of the SDK's mock runtime, with the messages in flight between them.
+    ///
+    /// Each operation or message runs as its own block, one microsecond after the previous one: its state is saved
+    /// afterwards and the messages it sent wait in the destination's inbox until [`Network::process_inbox`].
+    struct Network {
+        hub_chain: ChainId,
+        contracts: BTreeMap<ChainId, RpgHubContract>,
+        inboxes: BTreeMap<ChainId, Vec<Delivery>>,
+        signer: AccountOwner,
+        now: u64,
+    }
+
+    impl Network {
+        /// Creates the hub on `hub_chain`, signed by [`OWNER`].
+        async fn new(hub_chain: ChainId, max_achievements: u64) -> Self {
+            let mut network = Network {
+                hub_chain,
+                contracts: BTreeMap::new(),
+                inboxes: BTreeMap::new(),
+                signer: OWNER,
+                now: 0,
+            };
+            let parameters = HubParameters { max_achievements, admins: Vec::new() };
+            let mut contract = network.load(hub_chain, parameters).await;
+            contract.runtime.set_authenticated_signer(OWNER);
+            contract.instantiate(max_achievements).await;
+            contract.state.save().await.expect("Failed to save hub state");
+            network.contracts.insert(hub_chain, contract);
+            network
+        }
+
+        /// Opens the hub on a world chain as well.
+        async fn add_chain(&mut self, chain_id: ChainId) {
+            let parameters = self.contracts.get_mut(&self.hub_chain).unwrap().runtime.application_parameters();
+            let contract = self.load(chain_id, parameters).await;
+            self.contracts.insert(chain_id, contract);
+        }
+
+        async fn load(&mut self, chain_id: ChainId, parameters: HubParameters) -> RpgHubContract {
+            let runtime = ContractRuntime::new()
+                .with_application_parameters(parameters)
+                .with_application_id(ApplicationId::new(CryptoHash::test_hash("rpg_hub")).with_abi())
+                .with_application_creator_chain_id(self.hub_chain)
+                .with_chain_id(chain_id)
+                .with_system_time(Timestamp::from(self.now));
+            RpgHubContract::load(runtime).await
+        }
+
+        /// Executes `operation` in a block of `chain_id` signed by the current signer.
+        async fn execute(&mut self, chain_id: ChainId, operation: HubOperation) -> Result<(), HubError> {
+            self.call(chain_id, None, operation).await
+        }
+
+        /// Executes `operation` as called by application `caller` in a block of `chain_id`.
+        async fn call(&mut self, chain_id: ChainId, caller: Option<ApplicationId>, operation: HubOperation) -> Result<(), HubError> {
+            self.now += 1;
+            let contract = self.contracts.get_mut(&chain_id).expect("The hub does not run on this chain");
+            contract.runtime
+                .set_authenticated_signer(self.signer)
+                .set_authenticated_caller_id(caller)
+                .set_message_origin_chain_id(None)
+                .set_message_is_bouncing(None)
+                .set_system_time(Timestamp::from(self.now));
+            let response = contract.execute_operation(operation).await;
+            self.commit(chain_id).await;
+            response
+        }
+
+        /// Has the game `caller` of `chain_id` report an achievement of `player_id`.
+        async fn report_achievement(&mut self, chain_id: ChainId, caller: ApplicationId, player_id: &str, achievement_id: &str) {
+            self.call(chain_id, Some(caller), HubOperation::ReportAchievement {
+                hub_chain_id: self.hub_chain,
+                player_id: player_id.to_string(),
+                achievement_id: achievement_id.to_string(),
+                metadata: "{}".to_string(),
+            }).await.unwrap();
+        }
+
+        /// Has the game `caller` of `chain_id` report the level and new battle wins of `player_id`.
+        async fn report_progress(&mut self, chain_id: ChainId, caller: ApplicationId, player_id: &str, level: u64, battle_wins_gained: u64) {
+            self.call(chain_id, Some(caller), HubOperation::ReportPlayerProgress {
+                hub_chain_id: self.hub_chain,
+                player_id: player_id.to_string(),
+                level,
+                battle_wins_gained,
+            }).await.unwrap();
+        }
+
+        /// Executes the messages waiting for `chain_id`, in the order they were sent.
+        async fn process_inbox(&mut self, chain_id: ChainId) {
+            for delivery in self.inboxes.remove(&chain_id).unwrap_or_default() {
+                self.now += 1;
+                let contract = self.contracts.get_mut(&chain_id).expect("The hub does not run on this chain");
+                contract.runtime
+                    .set_authenticated_signer(delivery.signer)
+                    .set_authenticated_caller_id(None)
+                    .set_message_origin_chain_id(delivery.origin)
+                    .set_message_is_bouncing(false)
+                    .set_system_time(Timestamp::from(self.now));
+                contract.execute_message(delivery.message).await;
+                self.commit(chain_id).await;
+            }
+        }
+
+        /// Saves the state of `chain_id` and moves the messages it sent to their inboxes.
+        async fn commit(&mut self, chain_id: ChainId) {
+            let contract = self.contracts.get_mut(&chain_id).expect("The hub does not run on this chain");
+            contract.state.save().await.expect("Failed to save hub state");
+            let signer = contract.runtime.authenticated_signer();
+            let requests = std::mem::take(&mut *contract.runtime.created_send_message_requests());
+            for request in requests {
+                self.inboxes.entry(request.destination).or_default().push(Delivery {
+                    origin: chain_id,
+                    signer: if request.authenticated { signer } else { None },
+                    message: request.message,
+                });
+            }
+        }
+
+        /// Loads the saved state of `chain_id`.
+        async fn view(&self, chain_id: ChainId) -> HubState {
+            let context = self.contracts[&chain_id].runtime.root_view_storage_context();
+            HubState::load(context).await.expect("Failed to load hub state")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_world_chain_registration() {
+        let hub_chain = chain(0);
+        let world1 = chain(1);
+        let world2 = chain(2);
+
+        let mut network = Network::new(hub_chain, 100).await;
+        for (chain_id, world_region) in [(world1, "world1"), (world2, "world2")] {
+            network.execute(hub_chain, register(chain_id, world_region, game(world_region))).await.unwrap();
+        }
+
+        let state = network.view(hub_chain).await;
+        assert_eq!(*state.total_chains.get(), 2);
+
+        let info = state.world_chains.get(&world1).await.unwrap().unwrap();
+        assert_eq!(info.chain_id, world1);
+        assert_eq!(info.world_region, "world1");
+        assert_eq!(info.game_application_id, game("world1"));
+        assert!(info.active);
+    }
+
+    #[tokio::test]
+    async fn test_achievement_submission() {
+        let hub_chain = chain(0);
+        let world1 = chain(1);
+
+        let mut network = Network::new(hub_chain, 100).await;
+        network.add_chain(world1).await;
+        network.execute(hub_chain, register(world1, "world1", game("world1"))).await.unwrap();
+
+        let player_id = "hero".to_string();
+        for quest_id in ["first_blood", "dragon_slayer"] {
+            let achievement_id = format!("quest_{}", quest_id);
+            network.execute(hub_chain, HubOperation::RegisterAchievement {
+                definition: definition(&achievement_id, 10),
+            }).await.unwrap();
+
+            network.report_achievement(world1, game("world1"), &player_id, &achievement_id).await;
+        }
+
+        // Reports must come from an application, not straight from a signer
+        let response = network.execute(world1, HubOperation::ReportAchievement {
+            hub_chain_id: hub_chain,
+            player_id: "cheater".to_string(),
+            achievement_id: "quest_first_blood".to_string(),
+            metadata: "{}".to_string(),
+        }).await;
+        assert_eq!(response, Err(HubError::MissingCaller));
+
+        // and only the game registered for the chain is trusted
+        network.report_achievement(world1, game("impostor"), "cheater", "quest_first_blood").await;
+        network.process_inbox(hub_chain).await;
+
+        let state = network.view(hub_chain).await;
+        assert_eq!(*state.total_achievements.get(), 2);
+        assert!(state.player_achievements.get(&"cheater".to_string()).await.unwrap().is_none());
+
+        let achievements = state.player_achievements.get(&player_id).await.unwrap().unwrap();
+        assert_eq!(achievements.len(), 2);
+        assert_eq!(achievements[0].chain_id, world1);
+
+        let records = state.all_achievements.get(&"quest_dragon_slayer".to_string()).await.unwrap().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].player_id, player_id);
+    }
+
+    #[tokio::test]
+    async fn test_achievement_catalogue_rules() {
+        let hub_chain = chain(0);
+        let world1 = chain(1);
+
+        let mut network = Network::new(hub_chain, 2).await;
+        network.add_chain(world1).await;
+
+        for achievement_id in ["quest_first_blood", "quest_dragon_slayer"] {
+            network.execute(hub_chain, HubOperation::RegisterAchievement {
+                definition: definition(achievement_id, 10),
+            }).await.unwrap();
+        }
+
+        // The catalogue is capped by max_achievements and ids are unique
+        let response = network.execute(hub_chain, HubOperation::RegisterAchievement {
+            definition: definition("quest_treasure_hunter", 5),
+        }).await;
+        assert_eq!(response, Err(HubError::CatalogueFull(2)));
+
+        let response = network.execute(hub_chain, HubOperation::RegisterAchievement {
+            definition: definition("quest_first_blood", 50),
+        }).await;
+        assert_eq!(response, Err(HubError::AchievementAlreadyDefined("quest_first_blood".to_string())));
+
+        network.execute(hub_chain, register(world1, "world1", game("world1"))).await.unwrap();
+
+        // Reporting the same achievement again, or one outside the catalogue, does not inflate the totals
+        for achievement_id in ["quest_first_blood", "quest_first_blood", "quest_made_up"] {
+            network.report_achievement(world1, game("world1"), "hero", achievement_id).await;
+        }
+        network.process_inbox(hub_chain).await;
+
+        let state = network.view(hub_chain).await;
+        assert_eq!(*state.total_achievements.get(), 1);
+        assert_eq!(state.player_achievements.get(&"hero".to_string()).await.unwrap().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cross_region_leaderboards() {
+        let hub_chain = chain(0);
+        let world1 = chain(1);
+        let world2 = chain(2);
+
+        let mut network = Network::new(hub_chain, 100).await;
+        network.execute(hub_chain, HubOperation::RegisterAchievement {
+            definition: definition("quest_dragon_slayer", 50),
+        }).await.unwrap();
+        for (chain_id, world_region) in [(world1, "world1"), (world2, "world2")] {
+            network.add_chain(chain_id).await;
+            network.execute(hub_chain, register(chain_id, world_region, game(world_region))).await.unwrap();
+        }
+        let (game1, game2) = (game("world1"), game("world2"));
+
+        // Players from different regions report progress to the same hub
+        network.report_achievement(world1, game1, "knight", "quest_dragon_slayer").await;
+        network.report_progress(world1, game1, "knight", 4, 0).await;
+        network.report_progress(world1, game1, "knight", 4, 1).await;
+        network.report_progress(world2, game2, "mage", 7, 0).await;
+        network.report_progress(world2, game2, "mage", 7, 1).await;
+        network.report_progress(world2, game2, "mage", 7, 1).await;
+        network.report_progress(world1, game1, "archer", 7, 0).await;
+
+        // Progress cannot be reported without going through the game
+        let response = network.execute(world1, HubOperation::ReportPlayerProgress {
+            hub_chain_id: hub_chain,
+            player_id: "archer".to_string(),
+            level: 99,
+            battle_wins_gained: 10,
+        }).await;
+        assert_eq!(response, Err(HubError::MissingCaller));
+        network.process_inbox(hub_chain).await;
+
+        let state = network.view(hub_chain).await;
+
+        assert_eq!(state.leaderboard(LeaderboardKind::AchievementPoints).await
+            .into_iter().map(|entry| (entry.player_id, entry.score)).collect::<Vec<_>>(), [
+            ("knight".to_string(), 50),
+            ("archer".to_string(), 0),
+            ("mage".to_string(), 0),
+        ]);
+        // Ties are broken by player ID
+        assert_eq!(state.leaderboard(LeaderboardKind::Level).await
+            .into_iter().map(|entry| (entry.player_id, entry.score)).collect::<Vec<_>>(), [
+            ("archer".to_string(), 7),
+            ("mage".to_string(), 7),
+            ("knight".to_string(), 4),
+        ]);
+        assert_eq!(state.leaderboard(LeaderboardKind::BattleWins).await
+            .into_iter().map(|entry| (entry.player_id, entry.score)).collect::<Vec<_>>(), [
+            ("mage".to_string(), 2),
+            ("knight".to_string(), 1),
+            ("archer".to_string(), 0),
+        ]);
+
+        let mage = state.player_scores.get(&"mage".to_string()).await.unwrap().unwrap();
+        assert_eq!(mage.last_chain_id, Some(world2));
+    }
+
+    #[tokio::test]
+    async fn test_leaderboards_follow_dropping_scores() {
+        let hub_chain = chain(0);
+        let world = chain(1);
+
+        let mut network = Network::new(hub_chain, 100).await;
+        network.add_chain(world).await;
+        network.execute(hub_chain, register(world, "world1", game("world1"))).await.unwrap();
+        let game = game("world1");
+
+        // A full level leaderboard, and a player left out of it
+        for index in 0..LEADERBOARD_SIZE {
+            network.report_progress(world, game, &format!("player{:03}", index), 10, 0).await;
+        }
+        network.report_progress(world, game, "latecomer", 5, 0).await;
+        // Scores saturate instead of overflowing
+        network.report_progress(world, game, "latecomer", 5, u64::MAX).await;
+        network.report_progress(world, game, "latecomer", 5, 1).await;
+        network.process_inbox(hub_chain).await;
+
+        let state = network.view(hub_chain).await;
+        let level = state.leaderboard(LeaderboardKind::Level).await;
+        assert_eq!(level.len(), LEADERBOARD_SIZE as usize);
+        assert!(level.iter().all(|entry| entry.player_id != "latecomer"));
+        let wins = state.leaderboard(LeaderboardKind::BattleWins).await;
+        assert_eq!((wins[0].player_id.as_str(), wins[0].score), ("latecomer", u64::MAX));
+
+        // A level that drops below the player left out lets them onto the leaderboard
+        network.report_progress(world, game, "player000", 1, 0).await;
+        network.process_inbox(hub_chain).await;
+
+        let state = network.view(hub_chain).await;
+        let level = state.leaderboard(LeaderboardKind::Level).await;
+        assert_eq!(level.len(), LEADERBOARD_SIZE as usize);
+        assert!(level.iter().all(|entry| entry.player_id != "player000"));
+        let last = level.last().unwrap();
+        assert_eq!((last.rank, last.player_id.as_str(), last.score), (LEADERBOARD_SIZE, "latecomer", 5));
+    }
+
+    #[tokio::test]
+    async fn test_seasons() {
+        let hub_chain = chain(0);
+        let world1 = chain(1);
+
+        let mut network = Network::new(hub_chain, 100).await;
+        network.add_chain(world1).await;
+
+        let response = network.execute(hub_chain, HubOperation::EndSeason).await;
+        assert_eq!(response, Err(HubError::NoActiveSeason));
+
+        for (achievement_id, points) in [("quest_warm_up", 5), ("quest_first_blood", 10)] {
+            network.execute(hub_chain, HubOperation::RegisterAchievement {
+                definition: definition(achievement_id, points),
+            }).await.unwrap();
+        }
+        network.execute(hub_chain, register(world1, "world1", game("world1"))).await.unwrap();
+        network.report_achievement(world1, game("world1"), "hero", "quest_warm_up").await;
+        network.process_inbox(hub_chain).await;
+
+        network.execute(hub_chain, HubOperation::StartSeason {
+            name: "Spring".to_string(),
+        }).await.unwrap();
+
+        // Points earned before the season do not count towards it
+        let state = network.view(hub_chain).await;
+        assert!(state.leaderboard(LeaderboardKind::AchievementPoints).await.is_empty());
+        assert_eq!(state.player_score("hero").await.unwrap().achievement_points, 0);
+
+        // Only one season runs at a time, and only the hub chain manages them
+        let response = network.execute(hub_chain, HubOperation::StartSeason {
+            name: "Summer".to_string(),
+        }).await;
+        assert_eq!(response, Err(HubError::SeasonInProgress(1)));
+
+        let response = network.execute(world1, HubOperation::EndSeason).await;
+        assert_eq!(response, Err(HubError::NotHubChain));
+
+        network.report_achievement(world1, game("world1"), "hero", "quest_first_blood").await;
+        network.report_progress(world1, game("world1"), "hero", 3, 0).await;
+        network.report_progress(world1, game("world1"), "hero", 3, 1).await;
+        network.process_inbox(hub_chain).await;
+
+        network.execute(hub_chain, HubOperation::EndSeason).await.unwrap();
+
+        let state = network.view(hub_chain).await;
+        assert!(state.current_season.get().is_none());
+
+        let archive = state.season_archives.get(&1).await.unwrap().unwrap();
+        assert_eq!(archive.season.name, "Spring");
+        assert_eq!(archive.leaderboard(LeaderboardKind::AchievementPoints)[0].player_id, "hero");
+        assert_eq!(archive.leaderboard(LeaderboardKind::AchievementPoints)[0].score, 10);
+        assert_eq!(archive.leaderboard(LeaderboardKind::BattleWins)[0].score, 1);
+
+        // Seasonal counters start over, levels carry over
+        assert!(state.leaderboard(LeaderboardKind::BattleWins).await.is_empty());
+        let score = state.player_score("hero").await.unwrap();
+        assert_eq!(score.achievement_points, 0);
+        assert_eq!(score.battle_wins, 0);
+        assert_eq!(score.level, 3);
+
+        network.execute(hub_chain, HubOperation::StartSeason {
+            name: "Summer".to_string(),
+        }).await.unwrap();
+        let state = network.view(hub_chain).await;
+        assert_eq!(state.current_season.get().as_ref().unwrap().season_id, 2);
+    }
+
+    #[tokio::test]
+    async fn test_world_chain_lifecycle() {
+        let hub_chain = chain(0);
+        let world1 = chain(1);
+
+        let mut network = Network::new(hub_chain, 100).await;
+        network.add_chain(world1).await;
+        network.execute(hub_chain, register(world1, "world1", game("world1"))).await.unwrap();
+
+        let state = network.view(hub_chain).await;
+        let registered = state.world_chains.get(&world1).await.unwrap().unwrap();
+
+        // Heartbeats are attributed to the chain that sent them
+        network.execute(world1, HubOperation::SendHeartbeat {
+            hub_chain_id: hub_chain,
+        }).await.unwrap();
+        network.process_inbox(hub_chain).await;
+
+        let state = network.view(hub_chain).await;
+        let info = state.world_chains.get(&world1).await.unwrap().unwrap();
+        assert!(info.last_heartbeat > registered.last_heartbeat);
+
+        // Retiring a region happens on the hub chain only
+        let response = network.execute(world1, HubOperation::DeactivateWorldChain {
+            chain_id: world1,
+        }).await;
+        assert_eq!(response, Err(HubError::NotHubChain));
+
+        let response = network.execute(hub_chain, HubOperation::DeactivateWorldChain {
+            chain_id: chain(7),
+        }).await;
+        assert_eq!(response, Err(HubError::UnknownWorldChain(chain(7))));
+
+        network.execute(hub_chain, HubOperation::DeactivateWorldChain {
+            chain_id: world1,
+        }).await.unwrap();
+
+        let state = network.view(hub_chain).await;
+        assert!(!state.world_chains.get(&world1).await.unwrap().unwrap().active);
+
+        // Registering again reactivates the chain without counting it twice
+        network.execute(hub_chain, register(world1, "frontier", game("world1"))).await.unwrap();
+
+        let state = network.view(hub_chain).await;
+        assert_eq!(*state.total_chains.get(), 1);
+
+        let info = state.world_chains.get(&world1).await.unwrap().unwrap();
+        assert!(info.active);
+        assert_eq!(info.world_region, "frontier");
+        assert_eq!(info.registration_timestamp, registered.registration_timestamp);
+    }
+
+    #[tokio::test]
+    async fn test_admin_permissions() {
+        let hub_chain = chain(0);
+        let world1 = chain(1);
+        let world2 = chain(2);
+
+        let mut network = Network::new(hub_chain, 100).await;
+        network.execute(hub_chain, HubOperation::RegisterAchievement {
+            definition: definition("quest_first_blood", 10),
+        }).await.unwrap();
+        network.add_chain(world1).await;
+        network.add_chain(world2).await;
+        network.execute(hub_chain, register(world1, "world1", game("world1"))).await.unwrap();
+
+        // Only registered world chains can report achievements
+        network.report_achievement(world1, game("world1"), "hero", "quest_first_blood").await;
+        network.report_achievement(world2, game("world2"), "villain", "quest_first_blood").await;
+        network.process_inbox(hub_chain).await;
+
+        let state = network.view(hub_chain).await;
+        assert_eq!(*state.total_achievements.get(), 1);
+        assert!(state.player_achievements.get(&"villain".to_string()).await.unwrap().is_none());
+
+        // The creator is an admin and cannot leave the hub without one
+        assert_eq!(state.admins.indices().await.unwrap(), [OWNER]);
+        let response = network.execute(hub_chain, HubOperation::RemoveAdmin {
+            owner: OWNER,
+        }).await;
+        assert_eq!(response, Err(HubError::LastAdmin));
+
+        let other = AccountOwner::Address20([2; 20]);
+        network.execute(hub_chain, HubOperation::AddAdmin { owner: other }).await.unwrap();
+        network.execute(hub_chain, HubOperation::RemoveAdmin { owner: OWNER }).await.unwrap();
+
+        let response = network.execute(hub_chain, register(world2, "world2", game("world2"))).await;
+        assert_eq!(response, Err(HubError::Unauthorized(OWNER)));
+
+        let state = network.view(hub_chain).await;
+        assert_eq!(state.admins.indices().await.unwrap(), [other]);
+        assert_eq!(*state.total_chains.get(), 1);
+    }
+}
@@ -0,0 +1,413 @@
+// RPG Game Inventory
+// SPDX-License-Identifier: MIT
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::RpgGameError;
+
+/// Number of bag slots, named `inventory-1` to `inventory-24` like in the frontend
+pub const BAG_SLOTS: u64 = 24;
+/// Number of equipment slots, named `inventory-equip-1` to `inventory-equip-8`
+pub const EQUIP_SLOTS: u64 = 8;
+
+/// Returns the index in a slot name made of `prefix` and the index, if the index is written the canonical way.
+///
+/// Aliases like `inventory-01` or `inventory-+1` would otherwise name the same slot as `inventory-1` while
+/// comparing as a different one.
+fn slot_index(slot: &str, prefix: &str) -> Option<u64> {
+    let raw = slot.strip_prefix(prefix)?;
+    let index = raw.parse::<u64>().ok()?;
+    (index.to_string() == raw).then_some(index)
+}
+
+/// The two kinds of inventory slots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotKind {
+    Bag,
+    Equip,
+}
+
+impl SlotKind {
+    /// Returns the kind of `slot`, or `None` if it is not a valid slot name.
+    pub fn of(slot: &str) -> Option<SlotKind> {
+        let (kind, index, count) = if let Some(index) = slot_index(slot, "inventory-equip-") {
+            (SlotKind::Equip, index, EQUIP_SLOTS)
+        } else {
+            (SlotKind::Bag, slot_index(slot, "inventory-")?, BAG_SLOTS)
+        };
+        (1..=count).contains(&index).then_some(kind)
+    }
+
+    /// Returns the names of every slot of this kind, in order.
+    pub fn slots(self) -> impl Iterator<Item = String> {
+        let (prefix, count) = match self {
+            SlotKind::Bag => ("inventory-", BAG_SLOTS),
+            SlotKind::Equip => ("inventory-equip-", EQUIP_SLOTS),
+        };
+        (1..=count).map(move |index| format!("{}{}", prefix, index))
+    }
+}
+
+/// What an equipment slot holds, in frontend order from `inventory-equip-1` to `inventory-equip-8`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, async_graphql::Enum)]
+pub enum EquipmentSlot {
+    Weapon,
+    Offhand,
+    Head,
+    Chest,
+    Hands,
+    Legs,
+    Feet,
+    Accessory,
+}
+
+impl EquipmentSlot {
+    pub const ALL: [EquipmentSlot; EQUIP_SLOTS as usize] = [
+        EquipmentSlot::Weapon,
+        EquipmentSlot::Offhand,
+        EquipmentSlot::Head,
+        EquipmentSlot::Chest,
+        EquipmentSlot::Hands,
+        EquipmentSlot::Legs,
+        EquipmentSlot::Feet,
+        EquipmentSlot::Accessory,
+    ];
+
+    /// Returns what the equipment slot named `slot` holds, or `None` if it is not an equipment slot.
+    pub fn of(slot: &str) -> Option<EquipmentSlot> {
+        let index = slot_index(slot, "inventory-equip-")?;
+        EquipmentSlot::ALL.get(usize::try_from(index.checked_sub(1)?).ok()?).copied()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, async_graphql::Enum)]
+pub enum ItemRarity {
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+/// Stat changes an item grants while equipped
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, async_graphql::SimpleObject, async_graphql::InputObject)]
+#[graphql(input_name = "StatModifiersInput")]
+pub struct StatModifiers {
+    pub max_health: i64,
+    pub strength: i64,
+    pub wisdomness: i64,
+    pub benchpress: i64,
+    pub curl: i64,
+}
+
+impl StatModifiers {
+    /// Adds `other`'s modifiers to these.
+    pub fn add(&mut self, other: &StatModifiers) {
+        self.max_health = self.max_health.saturating_add(other.max_health);
+        self.strength = self.strength.saturating_add(other.strength);
+        self.wisdomness = self.wisdomness.saturating_add(other.wisdomness);
+        self.benchpress = self.benchpress.saturating_add(other.benchpress);
+        self.curl = self.curl.saturating_add(other.curl);
+    }
+
+    /// Applies a modifier to a base stat; stats never go below zero.
+    pub fn apply(base: u64, modifier: i64) -> u64 {
+        base.saturating_add_signed(modifier)
+    }
+}
+
+/// An item players can hold, as registered in the chain's item catalogue
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, async_graphql::SimpleObject, async_graphql::InputObject)]
+#[graphql(input_name = "ItemDefinitionInput")]
+pub struct ItemDefinition {
+    pub id: String,
+    pub name: String,
+    /// Equipment slot the item can be equipped in; `None` for items that stay in the bag
+    pub slot_type: Option<EquipmentSlot>,
+    pub stat_modifiers: StatModifiers,
+    pub rarity: ItemRarity,
+    /// Most units a single slot can hold
+    pub stack_size: u64,
+    pub tradable: bool,
+    /// Parameters the frontend reads from the item, e.g. a weapon's `damage`; every granted unit gets them
+    #[serde(default)]
+    pub params: ItemParams,
+}
+
+impl ItemDefinition {
+    /// Checks that `item` is a valid stack of this item for the slot it sits in.
+    pub fn check(&self, item: &InventoryItem) -> Result<(), RpgGameError> {
+        if item.quantity == 0 || item.quantity > self.stack_size {
+            return Err(RpgGameError::InvalidQuantity {
+                item_id: item.item_id.clone(),
+                quantity: item.quantity,
+                stack_size: self.stack_size,
+            });
+        }
+        let fits = match SlotKind::of(&item.slot) {
+            Some(SlotKind::Bag) => true,
+            Some(SlotKind::Equip) => self.slot_type.is_some() && EquipmentSlot::of(&item.slot) == self.slot_type,
+            None => false,
+        };
+        if !fits {
+            return Err(RpgGameError::IncompatibleSlot {
+                item_id: item.item_id.clone(),
+                slot: item.slot.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Free-form item parameters, exposed as the `JSON` GraphQL scalar.
+///
+/// BCS cannot deserialize arbitrary JSON, so binary formats store the parameters as JSON text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ItemParams(pub serde_json::Value);
+
+impl Serialize for ItemParams {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            self.0.serialize(serializer)
+        } else {
+            self.0.to_string().serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ItemParams {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            serde_json::Value::deserialize(deserializer).map(ItemParams)
+        } else {
+            let text = String::deserialize(deserializer)?;
+            serde_json::from_str(&text).map(ItemParams).map_err(D::Error::custom)
+        }
+    }
+}
+
+#[async_graphql::Scalar(name = "JSON")]
+impl async_graphql::ScalarType for ItemParams {
+    fn parse(value: async_graphql::Value) -> async_graphql::InputValueResult<Self> {
+        Ok(ItemParams(value.into_json()?))
+    }
+
+    fn to_value(&self) -> async_graphql::Value {
+        async_graphql::Value::from_json(self.0.clone()).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, async_graphql::SimpleObject, async_graphql::InputObject)]
+#[graphql(input_name = "InventoryInput")]
+pub struct InventoryData {
+    pub items: Vec<InventoryItem>,
+}
+
+impl InventoryData {
+    /// Returns the item stored in `slot`, if any.
+    pub fn item_in_slot(&self, slot: &str) -> Option<&InventoryItem> {
+        self.items.iter().find(|item| item.slot == slot)
+    }
+
+    /// Checks that every item sits in a valid slot of its own.
+    pub fn validate(&self) -> Result<(), RpgGameError> {
+        for (index, item) in self.items.iter().enumerate() {
+            if SlotKind::of(&item.slot).is_none() {
+                return Err(RpgGameError::InvalidSlot(item.slot.clone()));
+            }
+            if self.items[..index].iter().any(|other| other.slot == item.slot) {
+                return Err(RpgGameError::SlotOccupied(item.slot.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the items in equipment slots.
+    pub fn equipped_items(&self) -> impl Iterator<Item = &InventoryItem> {
+        self.items.iter().filter(|item| SlotKind::of(&item.slot) == Some(SlotKind::Equip))
+    }
+
+    /// Returns the first empty slot of the given kind.
+    pub fn first_free_slot(&self, kind: SlotKind) -> Option<String> {
+        kind.slots().find(|slot| self.item_in_slot(slot).is_none())
+    }
+
+    /// Puts a new stack of items in the bag, in `slot` or else the first free bag slot, and returns the slot used.
+    pub fn add_item(
+        &mut self,
+        item_id: String,
+        quantity: u64,
+        params: ItemParams,
+        slot: Option<String>,
+    ) -> Result<String, RpgGameError> {
+        let slot = self.free_slot(slot, SlotKind::Bag)?;
+        self.items.push(InventoryItem {
+            slot: slot.clone(),
+            item_id,
+            quantity,
+            params,
+        });
+        Ok(slot)
+    }
+
+    /// Returns this inventory with its stacks moved to the slots of `items`, which must list exactly the stacks held.
+    ///
+    /// Stacks are matched by item and quantity, and keep the parameters they were granted with.
+    pub fn rearrange(&self, items: Vec<InventoryItem>) -> Result<InventoryData, RpgGameError> {
+        let mut held = self.items.clone();
+        let mut arranged = Vec::with_capacity(items.len());
+        for item in items {
+            let matches = |other: &InventoryItem| other.item_id == item.item_id && other.quantity == item.quantity;
+            let index = held.iter().position(|other| matches(other) && other.slot == item.slot)
+                .or_else(|| held.iter().position(matches))
+                .ok_or_else(|| RpgGameError::InventoryMismatch(format!("{} units of item {} are not held", item.quantity, item.item_id)))?;
+            arranged.push(InventoryItem {
+                slot: item.slot,
+                ..held.swap_remove(index)
+            });
+        }
+        if let Some(item) = held.first() {
+            return Err(RpgGameError::InventoryMismatch(format!("the stack in slot {} is missing", item.slot)));
+        }
+
+        let inventory = InventoryData { items: arranged };
+        inventory.validate()?;
+        Ok(inventory)
+    }
+
+    /// Takes the item out of `slot`.
+    pub fn remove_item(&mut self, slot: &str) -> Result<InventoryItem, RpgGameError> {
+        let index = self.items.iter().position(|item| item.slot == slot)
+            .ok_or_else(|| RpgGameError::EmptySlot(slot.to_string()))?;
+        Ok(self.items.remove(index))
+    }
+
+    /// Moves the item in `from_slot`, which must be a `from` slot, to `to_slot` or else the first free `to` slot.
+    ///
+    /// Returns the slot the item landed in.
+    pub fn relocate_item(
+        &mut self,
+        from_slot: &str,
+        from: SlotKind,
+        to_slot: Option<String>,
+        to: SlotKind,
+    ) -> Result<String, RpgGameError> {
+        if SlotKind::of(from_slot) != Some(from) {
+            return Err(RpgGameError::InvalidSlot(from_slot.to_string()));
+        }
+        let to_slot = self.free_slot(to_slot, to)?;
+        let item = self.items.iter_mut().find(|item| item.slot == from_slot)
+            .ok_or_else(|| RpgGameError::EmptySlot(from_slot.to_string()))?;
+        item.slot = to_slot.clone();
+        Ok(to_slot)
+    }
+
+    /// Takes `quantity` units of `item_id` out of the bag, splitting the last stack if needed.
+    ///
+    /// Stacks are taken in slot order; equipped items are never taken.
+    pub fn take_items(&mut self, item_id: &str, quantity: u64) -> Result<Vec<InventoryItem>, RpgGameError> {
+        let available = self.items.iter()
+            .filter(|item| item.item_id == item_id && SlotKind::of(&item.slot) == Some(SlotKind::Bag))
+            .fold(0u64, |total, item| total.saturating_add(item.quantity));
+        if available < quantity {
+            return Err(RpgGameError::NotEnoughItems {
+                item_id: item_id.to_string(),
+                quantity,
+                available,
+            });
+        }
+
+        let mut taken = Vec::new();
+        let mut remaining = quantity;
+        for slot in SlotKind::Bag.slots() {
+            if remaining == 0 {
+                break;
+            }
+            let Some(index) = self.items.iter().position(|item| item.slot == slot && item.item_id == item_id) else {
+                continue;
+            };
+            if self.items[index].quantity <= remaining {
+                remaining -= self.items[index].quantity;
+                taken.push(self.items.remove(index));
+            } else {
+                let item = &mut self.items[index];
+                item.quantity -= remaining;
+                taken.push(InventoryItem {
+                    quantity: remaining,
+                    ..item.clone()
+                });
+                remaining = 0;
+            }
+        }
+        Ok(taken)
+    }
+
+    /// Takes `quantity` units out of the stack in bag slot `slot`.
+    pub fn take_from_slot(&mut self, slot: &str, quantity: u64) -> Result<InventoryItem, RpgGameError> {
+        if SlotKind::of(slot) != Some(SlotKind::Bag) {
+            return Err(RpgGameError::InvalidSlot(slot.to_string()));
+        }
+        let index = self.items.iter().position(|item| item.slot == slot)
+            .ok_or_else(|| RpgGameError::EmptySlot(slot.to_string()))?;
+        let item = &mut self.items[index];
+        if item.quantity < quantity {
+            return Err(RpgGameError::NotEnoughItems {
+                item_id: item.item_id.clone(),
+                quantity,
+                available: item.quantity,
+            });
+        }
+        if item.quantity == quantity {
+            return Ok(self.items.remove(index));
+        }
+        item.quantity -= quantity;
+        Ok(InventoryItem {
+            quantity,
+            ..item.clone()
+        })
+    }
+
+    /// Puts each stack of `items` in its own free bag slot.
+    pub fn put_items(&mut self, items: Vec<InventoryItem>) -> Result<(), RpgGameError> {
+        for item in items {
+            self.add_item(item.item_id, item.quantity, item.params, None)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that `slot` is an empty slot of the given kind, or picks the first one if `slot` is `None`.
+    fn free_slot(&self, slot: Option<String>, kind: SlotKind) -> Result<String, RpgGameError> {
+        match slot {
+            Some(slot) if SlotKind::of(&slot) != Some(kind) => Err(RpgGameError::InvalidSlot(slot)),
+            Some(slot) if self.item_in_slot(&slot).is_some() => Err(RpgGameError::SlotOccupied(slot)),
+            Some(slot) => Ok(slot),
+            None => self.first_free_slot(kind).ok_or(RpgGameError::InventoryFull),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, async_graphql::SimpleObject, async_graphql::InputObject)]
+#[graphql(input_name = "InventoryItemInput")]
+pub struct InventoryItem {
+    pub slot: String,
+    /// The frontend spells it `itemId`
+    #[serde(alias = "itemId")]
+    pub item_id: String,
+    /// Units stacked in the slot
+    #[serde(default = "default_quantity")]
+    pub quantity: u64,
+    #[serde(default)]
+    pub params: ItemParams,  // Flexible params structure
+}
+
+fn default_quantity() -> u64 {
+    1
+}
+
+/// Units of an item listed in a trade
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, async_graphql::SimpleObject, async_graphql::InputObject)]
+#[graphql(input_name = "TradeItemInput")]
+pub struct TradeItem {
+    pub item_id: String,
+    pub quantity: u64,
+}
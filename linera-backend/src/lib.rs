@@ -2,20 +2,105 @@
 // SPDX-License-Identifier: MIT
 
 use async_graphql::{Request, Response};
-use linera_sdk::linera_base_types::{ContractAbi, ServiceAbi, ChainId};
+use linera_sdk::linera_base_types::{AccountOwner, ApplicationId, BcsHashable, ContractAbi, CryptoHash, ServiceAbi, ChainId};
 use serde::{Deserialize, Serialize};
 
+pub mod hub_abi;
+pub mod hub_state;
+pub mod inventory;
+pub mod progression;
+pub mod shop;
+pub mod state;
+
+pub use hub_abi::{HubMessage, HubOperation, HubParameters, RpgHubAbi};
+pub use inventory::{
+    EquipmentSlot, InventoryData, InventoryItem, ItemDefinition, ItemParams, ItemRarity, SlotKind, StatModifiers,
+    TradeItem,
+};
+pub use shop::{Shop, ShopDefinition, ShopItem, ShopListing};
+
 pub struct RpgGameAbi;
 
 /// Application parameters: Hub application ID and chain ID for multi-chain features
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Parameters {
     pub world_region: String,  // The world region this chain represents
+    /// Hub application that milestones are reported to
+    #[serde(default)]
+    pub hub_application_id: Option<ApplicationId<RpgHubAbi>>,
+    /// Chain the hub application aggregates achievements on
+    #[serde(default)]
+    pub hub_chain_id: Option<ChainId>,
+    /// Rules every `SavePlayerState` payload must satisfy
+    #[serde(default)]
+    pub save_rules: SaveRules,
+    /// Owners allowed to manage the item catalogue, balances and shops, besides the account that created the application
+    #[serde(default)]
+    pub admins: Vec<AccountOwner>,
+}
+
+/// Invariants enforced on `SavePlayerState`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaveRules {
+    /// Experience, level and stats only change through `AddExperience`; the rules below apply otherwise
+    pub authoritative_progression: bool,
+    /// Refuse `health` above `max_health`
+    pub health_within_max: bool,
+    /// Refuse experience or level going down between saves
+    pub monotonic_progression: bool,
+    /// Most levels a player may gain between two saves; each stat may grow by that many levels' worth
+    pub max_levels_per_save: u64,
+}
+
+impl Default for SaveRules {
+    fn default() -> Self {
+        SaveRules {
+            authoritative_progression: true,
+            health_within_max: true,
+            monotonic_progression: true,
+            max_levels_per_save: 1,
+        }
+    }
+}
+
+/// A `SaveRules` invariant a player state can violate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SaveInvariant {
+    HealthWithinMax,
+    ExperienceNotDecreasing,
+    LevelNotDecreasing,
+    MaxLevelsPerSave,
+    MaxStatGainPerSave,
+}
+
+impl std::fmt::Display for SaveInvariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SaveInvariant::HealthWithinMax => "health within max",
+            SaveInvariant::ExperienceNotDecreasing => "experience not decreasing",
+            SaveInvariant::LevelNotDecreasing => "level not decreasing",
+            SaveInvariant::MaxLevelsPerSave => "max levels per save",
+            SaveInvariant::MaxStatGainPerSave => "max stat gain per save",
+        };
+        f.write_str(name)
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub enum RpgGameOperation {
+    /// Register a new player owned by the signer of the block
+    RegisterPlayer {
+        player_id: String,
+    },
+    /// Hand ownership of a player over to another account
+    TransferPlayerOwnership {
+        player_id: String,
+        new_owner: AccountOwner,
+    },
     /// Save player state to the blockchain
+    ///
+    /// Experience, level and stats must match the chain's record; they only change through `AddExperience`.
+    /// A new character is saved with the starting stats of [`progression`].
     SavePlayerState {
         player_id: String,
         health: u64,
@@ -27,26 +112,137 @@ pub enum RpgGameOperation {
         experience: u64,
         level: u64,
     },
-    /// Save inventory to the blockchain
+    /// Grant experience to a player, applying any level-ups it earns; admins only
+    AddExperience {
+        player_id: String,
+        amount: u64,
+    },
+    /// Add currency to a player's balance; admins only
+    CreditCurrency {
+        player_id: String,
+        amount: u64,
+        reason: String,
+    },
+    /// Take currency from a player's balance; admins only
+    DebitCurrency {
+        player_id: String,
+        amount: u64,
+        reason: String,
+    },
+    /// Pay currency to another player, on this or another chain
+    TransferCurrency {
+        player_id: String,
+        recipient_id: String,
+        recipient_chain: ChainId,
+        amount: u64,
+    },
+    /// Add or replace a shop of this region, with full stock; admins only
+    DefineShop {
+        shop: ShopDefinition,
+    },
+    /// Buy items from a shop into the player's bag
+    BuyItem {
+        player_id: String,
+        shop_id: String,
+        item_id: String,
+        quantity: u64,
+    },
+    /// Sell units of the item in a bag slot to a shop
+    SellItem {
+        player_id: String,
+        shop_id: String,
+        slot: String,
+        quantity: u64,
+    },
+    /// Rearrange the items the player holds; the chain keeps the held stacks and their parameters
+    ///
+    /// Items are only granted by admins, shops and trades, so `items` must list exactly the stacks held.
     SaveInventory {
         player_id: String,
-        inventory: String,  // JSON string of inventory
+        items: Vec<InventoryItem>,
+    },
+    /// Add or replace a quest of this chain, with the currency it pays on completion; admins only
+    DefineQuest {
+        definition: QuestDefinition,
+    },
+    /// Mark a quest of this chain completed for a player and pay its reward; admins only
+    CompleteQuest {
+        player_id: String,
+        quest_id: String,
+    },
+    /// Add or replace an item in the catalogue; admins only
+    DefineItem {
+        definition: ItemDefinition,
+    },
+    /// Grant a new stack of an item, with its catalogue parameters, in `slot` or else the first free bag slot; admins only
+    AddItem {
+        player_id: String,
+        item_id: String,
+        quantity: u64,
+        slot: Option<String>,
+    },
+    /// Take the item out of a slot
+    RemoveItem {
+        player_id: String,
+        slot: String,
+    },
+    /// Move an item between two bag slots; the destination must be empty
+    MoveItem {
+        player_id: String,
+        from_slot: String,
+        to_slot: String,
+    },
+    /// Move an item from the bag to an empty equipment slot
+    EquipItem {
+        player_id: String,
+        from_slot: String,
+        equip_slot: String,
+    },
+    /// Move an equipped item back to the bag, in `to_slot` or else the first free bag slot
+    UnequipItem {
+        player_id: String,
+        equip_slot: String,
+        to_slot: Option<String>,
+    },
+    /// Offer items from the player's bag in exchange for items of another player, on this or another chain
+    ///
+    /// The offered items are held in escrow until the trade is accepted, rejected or cancelled.
+    ProposeTrade {
+        player_id: String,
+        counterparty_id: String,
+        counterparty_chain: ChainId,
+        offered: Vec<TradeItem>,
+        requested: Vec<TradeItem>,
+    },
+    /// Accept a pending trade, on the counterparty's chain
+    AcceptTrade {
+        trade_id: String,
+    },
+    /// Reject a pending trade, on the counterparty's chain
+    RejectTrade {
+        trade_id: String,
+    },
+    /// Withdraw a pending trade, on the proposer's chain
+    CancelTrade {
+        trade_id: String,
+    },
+    /// Move trade items that did not fit in the player's bag into it
+    ClaimTradeItems {
+        trade_id: String,
     },
     /// Save quests to the blockchain
+    ///
+    /// Quests of this chain's catalogue must keep the completion recorded by `CompleteQuest`.
     SaveQuests {
         player_id: String,
-        quests: String,  // JSON string of quests
+        quests: Vec<QuestData>,
     },
     /// Transfer player to another chain (cross-chain transfer)
+    ///
+    /// The player's state, inventory and quests are read from this chain's storage.
     TransferPlayer {
         player_id: String,
         destination_chain: ChainId,
-        /// Include complete state for transfer
-        player_state: PlayerState,
-        inventory: String,
-        quests: String,
-        /// Authentication token to prevent unauthorized transfers
-        auth_token: String,
     },
     /// Join a guild on another chain
     JoinGuild {
@@ -64,9 +260,34 @@ pub enum RpgGameOperation {
         damage_taken: u64,
         experience_gained: u64,
     },
+    /// Pay currency to the player of a recorded battle, once per battle; admins only
+    RewardBattle {
+        battle_id: String,
+        amount: u64,
+    },
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, async_graphql::SimpleObject, async_graphql::InputObject)]
+#[graphql(input_name = "QuestInput")]
+pub struct QuestData {
+    pub id: String,
+    pub title: String,
+    pub text: String,
+    pub completed: bool,
+    pub progress: u64,  // For quests that track progress (e.g., kill 10 monsters)
+}
+
+/// A quest defined on chain; only these pay a reward and count as achievements when a player completes them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, async_graphql::SimpleObject, async_graphql::InputObject)]
+#[graphql(input_name = "QuestDefinitionInput")]
+pub struct QuestDefinition {
+    pub id: String,
+    pub title: String,
+    /// Currency paid when `CompleteQuest` completes the quest for a player
+    pub reward: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct PlayerState {
     pub health: u64,
     pub max_health: u64,
@@ -78,6 +299,72 @@ pub struct PlayerState {
     pub level: u64,
 }
 
+/// Everything a player transfer token commits to.
+///
+/// The token is a digest, not a secret: the destination chain recomputes it from the message it received, the
+/// message's authenticated signer and its origin chain. It ties the transferred state, inventory, quests and balance
+/// to that route and nonce, and lets the destination refuse a transfer it already accepted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferTicket {
+    pub player_id: String,
+    pub owner: AccountOwner,
+    pub source_chain: ChainId,
+    pub destination_chain: ChainId,
+    pub nonce: u64,
+    pub player_state: PlayerState,
+    pub inventory: String,
+    pub quests: String,
+    pub balance: u64,
+}
+
+impl BcsHashable<'_> for TransferTicket {}
+
+impl TransferTicket {
+    /// Returns the token for this ticket.
+    pub fn token(&self) -> String {
+        CryptoHash::new(self).to_string()
+    }
+
+    /// Checks a received `auth_token` against this ticket; `consumed` tells whether the destination accepted it before.
+    pub fn check(&self, auth_token: &str, consumed: bool) -> Result<(), RpgGameError> {
+        if auth_token != self.token() {
+            return Err(RpgGameError::InvalidTransferToken);
+        }
+        if consumed {
+            return Err(RpgGameError::TransferTokenReused);
+        }
+        Ok(())
+    }
+}
+
+/// Lifecycle of an outgoing player transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, async_graphql::Enum)]
+pub enum TransferStatus {
+    /// Sent to the destination chain; the player is locked until it is acknowledged
+    Pending,
+    /// Acknowledged by the destination chain; the player now lives there
+    Completed,
+    /// Refused by the destination chain; the player stays on this chain
+    Failed,
+}
+
+/// Lifecycle of a trade between two players.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, async_graphql::Enum)]
+pub enum TradeStatus {
+    /// Waiting for the counterparty; the offered items are in escrow
+    Pending,
+    /// Accepted on the counterparty's chain, waiting for the proposer's chain to settle
+    Accepted,
+    /// Both sides received their items
+    Completed,
+    /// Refused by the counterparty
+    Rejected,
+    /// Withdrawn by the proposer
+    Cancelled,
+    /// Refused by the counterparty's chain, or a trade message bounced
+    Failed,
+}
+
 /// Cross-chain message payloads for player transfers and other multi-chain features
 #[derive(Debug, Deserialize, Serialize)]
 pub enum RpgGameMessage {
@@ -87,7 +374,67 @@ pub enum RpgGameMessage {
         player_state: PlayerState,
         inventory: String,
         quests: String,
+        /// Nonce the source chain issued the transfer token for
+        nonce: u64,
+        /// Token of the [`TransferTicket`] the source chain issued for this transfer
         auth_token: String,
+        /// Currency the player carries along
+        balance: u64,
+    },
+    /// The destination chain stored the transferred player
+    PlayerTransferAcknowledged {
+        player_id: String,
+        nonce: u64,
+    },
+    /// The destination chain refused a player transfer
+    PlayerTransferRejected {
+        player_id: String,
+        nonce: u64,
+        error: RpgGameError,
+    },
+    /// A trade proposed to a player of the destination chain; the offered items stay in escrow on the source chain
+    TradeProposed {
+        trade_id: String,
+        proposer_id: String,
+        counterparty_id: String,
+        offered: Vec<TradeItem>,
+        requested: Vec<TradeItem>,
+    },
+    /// The proposer asks to withdraw a pending trade
+    TradeCancelRequested {
+        trade_id: String,
+    },
+    /// The counterparty's chain closed a trade without a swap, so the proposer gets the escrowed items back
+    TradeClosed {
+        trade_id: String,
+        status: TradeStatus,
+        failure_reason: Option<String>,
+    },
+    /// The counterparty accepted a trade and hands over the requested items
+    TradeAccepted {
+        trade_id: String,
+        items: Vec<InventoryItem>,
+    },
+    /// The proposer's chain released the escrowed items to the counterparty
+    TradeSettled {
+        trade_id: String,
+        items: Vec<InventoryItem>,
+    },
+    /// Currency paid to a player of the destination chain; the source chain already debited the sender
+    CurrencyTransfer {
+        transfer_id: String,
+        sender_id: String,
+        recipient_id: String,
+        amount: u64,
+    },
+    /// The destination chain credited the recipient of a currency transfer
+    CurrencyTransferAcknowledged {
+        transfer_id: String,
+    },
+    /// The destination chain refused a currency transfer, so the sender is refunded
+    CurrencyTransferRejected {
+        transfer_id: String,
+        error: RpgGameError,
     },
     /// Join a guild request from another chain
     GuildJoinRequest {
@@ -106,15 +453,192 @@ pub enum RpgGameMessage {
     },
 }
 
+/// Successful outcome of an [`RpgGameOperation`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RpgGameResponse {
+    Ok,
+    /// Experience was added to a player
+    ExperienceAdded {
+        level: u64,
+        levels_gained: u64,
+    },
+    /// An item was put in a slot
+    ItemPlaced {
+        slot: String,
+    },
+    /// A trade was proposed and its offered items put in escrow
+    TradeProposed {
+        trade_id: String,
+    },
+    /// Items were bought into a bag slot
+    ItemBought {
+        slot: String,
+        balance: u64,
+    },
+    /// A player's currency balance after a ledger operation
+    Balance {
+        balance: u64,
+    },
+    /// Currency was debited and sent to another chain
+    CurrencyTransferStarted {
+        transfer_id: String,
+        balance: u64,
+    },
+    /// A player transfer was sent to the destination chain
+    TransferStarted {
+        nonce: u64,
+        auth_token: String,
+    },
+}
+
+/// Reasons an [`RpgGameOperation`] or a cross-chain message is refused
+///
+/// A refused operation responds with this error and keeps none of its changes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+pub enum RpgGameError {
+    #[error("player {0} is not registered")]
+    UnknownPlayer(String),
+    #[error("player {0} is already registered")]
+    PlayerAlreadyRegistered(String),
+    #[error("player {0} already exists on this chain")]
+    PlayerAlreadyExists(String),
+    #[error("player {0} has no saved state")]
+    MissingPlayerState(String),
+    #[error("operation requires an authenticated signer")]
+    MissingSigner,
+    #[error("signer does not own player {0}")]
+    Unauthorized(String),
+    #[error("player {0} is transferring or has left this chain")]
+    TransferLocked(String),
+    #[error("{field} {actual} is inconsistent with the recorded progression, expected {expected}")]
+    InconsistentProgression {
+        field: String,
+        expected: u64,
+        actual: u64,
+    },
+    #[error("player state violates the {invariant} rule: {details}")]
+    SaveRuleViolated {
+        invariant: SaveInvariant,
+        details: String,
+    },
+    #[error("malformed inventory JSON: {0}")]
+    MalformedInventory(String),
+    #[error("{0} is not a valid slot for this operation")]
+    InvalidSlot(String),
+    #[error("slot {0} is already occupied")]
+    SlotOccupied(String),
+    #[error("slot {0} is empty")]
+    EmptySlot(String),
+    #[error("no free slot left in the bag")]
+    InventoryFull,
+    #[error("inventory does not match the items held: {0}")]
+    InventoryMismatch(String),
+    #[error("operation is reserved to admins")]
+    NotAdmin,
+    #[error("item {0} is not in the catalogue")]
+    UnknownItem(String),
+    #[error("item {item_id} cannot be stored in slot {slot}")]
+    IncompatibleSlot {
+        item_id: String,
+        slot: String,
+    },
+    #[error("{quantity} units of item {item_id} do not fit a stack of {stack_size}")]
+    InvalidQuantity {
+        item_id: String,
+        quantity: u64,
+        stack_size: u64,
+    },
+    #[error("item {0} cannot be traded")]
+    ItemNotTradable(String),
+    #[error("{quantity} units of item {item_id} requested, only {available} in the bag")]
+    NotEnoughItems {
+        item_id: String,
+        quantity: u64,
+        available: u64,
+    },
+    #[error("trade {0} does not exist on this chain")]
+    UnknownTrade(String),
+    #[error("trade {0} is no longer pending")]
+    TradeNotPending(String),
+    #[error("invalid trade: {0}")]
+    InvalidTrade(String),
+    #[error("player {player_id} has trade {trade_id} in progress")]
+    TradeInProgress {
+        player_id: String,
+        trade_id: String,
+    },
+    #[error("the counterparty's chain rejected the trade message")]
+    TradeBounced,
+    #[error("invalid item definition: {0}")]
+    InvalidItemDefinition(String),
+    #[error("malformed quests JSON: {0}")]
+    MalformedQuests(String),
+    #[error("quest {0} is not in the catalogue")]
+    UnknownQuest(String),
+    #[error("player {player_id} already completed quest {quest_id}")]
+    QuestAlreadyCompleted {
+        player_id: String,
+        quest_id: String,
+    },
+    #[error("quest {quest_id} must be saved with the completion recorded on chain, completed: {completed}")]
+    QuestCompletionMismatch {
+        quest_id: String,
+        completed: bool,
+    },
+    #[error("invalid battle result {0}, expected 0 (loss), 1 (draw) or 2 (win)")]
+    InvalidBattleResult(u64),
+    #[error("battle {0} is already recorded")]
+    DuplicateBattle(String),
+    #[error("battle {0} is not recorded on this chain")]
+    UnknownBattle(String),
+    #[error("battle {0} was already rewarded")]
+    BattleAlreadyRewarded(String),
+    #[error("a player with {max_health} effective max health cannot survive {damage_taken} damage")]
+    ImplausibleBattle {
+        damage_taken: u64,
+        max_health: u64,
+    },
+    #[error("amount must be greater than 0")]
+    InvalidAmount,
+    #[error("player {player_id} has a balance of {balance}, cannot pay {amount}")]
+    InsufficientFunds {
+        player_id: String,
+        balance: u64,
+        amount: u64,
+    },
+    #[error("balance of player {0} would overflow")]
+    BalanceOverflow(String),
+    #[error("invalid currency transfer: {0}")]
+    InvalidCurrencyTransfer(String),
+    #[error("shop {0} does not exist in this region")]
+    UnknownShop(String),
+    #[error("shop {shop_id} does not trade item {item_id}")]
+    NotInShop {
+        shop_id: String,
+        item_id: String,
+    },
+    #[error("shop {shop_id} has {stock} units of item {item_id} left")]
+    OutOfStock {
+        shop_id: String,
+        item_id: String,
+        stock: u64,
+    },
+    #[error("invalid shop: {0}")]
+    InvalidShop(String),
+    #[error("invalid transfer token")]
+    InvalidTransferToken,
+    #[error("transfer token was already used")]
+    TransferTokenReused,
+    #[error("the destination chain rejected the transfer message")]
+    TransferBounced,
+}
+
 impl ContractAbi for RpgGameAbi {
     type Operation = RpgGameOperation;
-    type Response = ();
+    type Response = Result<RpgGameResponse, RpgGameError>;
 }
 
 impl ServiceAbi for RpgGameAbi {
     type Query = Request;
     type QueryResponse = Response;
 }
-
-#[cfg(test)]
-mod tests;
\ No newline at end of file
@@ -0,0 +1,51 @@
+// RPG Game Progression Rules
+// SPDX-License-Identifier: MIT
+
+/// Experience awarded per level is compared against `BASE_EXPERIENCE * 2^(level - 1)`.
+pub const BASE_EXPERIENCE: u64 = 100;
+
+/// Highest level a player can reach
+pub const MAX_LEVEL: u64 = 100;
+
+/// Max health of a new character
+pub const STARTING_MAX_HEALTH: u64 = 100;
+/// Strength of a new character
+pub const STARTING_STRENGTH: u64 = 10;
+/// Wisdomness of a new character
+pub const STARTING_WISDOMNESS: u64 = 5;
+/// Benchpress of a new character
+pub const STARTING_BENCHPRESS: u64 = 5;
+/// Curl of a new character
+pub const STARTING_CURL: u64 = 5;
+
+/// Strength gained on each level-up
+pub const STRENGTH_PER_LEVEL: u64 = 1;
+/// Wisdomness gained on each level-up
+pub const WISDOMNESS_PER_LEVEL: u64 = 1;
+/// Benchpress gained on each level-up
+pub const BENCHPRESS_PER_LEVEL: u64 = 1;
+/// Curl gained on each level-up
+pub const CURL_PER_LEVEL: u64 = 2;
+
+/// Returns the total experience a player at `level` needs to reach the next level.
+pub fn experience_required(level: u64) -> u64 {
+    let exponent = u32::try_from(level.saturating_sub(1)).unwrap_or(u32::MAX);
+    2u64.checked_pow(exponent)
+        .and_then(|factor| factor.checked_mul(BASE_EXPERIENCE))
+        .unwrap_or(u64::MAX)
+}
+
+/// Returns the level a player starting at `level` reaches with `experience` in total, up to [`MAX_LEVEL`].
+pub fn level_for_experience(level: u64, experience: u64) -> u64 {
+    let mut level = level.max(1);
+    while level < MAX_LEVEL && experience >= experience_required(level) {
+        level += 1;
+    }
+    level
+}
+
+/// Levels that earn a hub achievement when reached
+pub const LEVEL_MILESTONES: [u64; 5] = [5, 10, 20, 30, 50];
+
+/// Consecutive battle wins that earn a hub achievement
+pub const WIN_STREAK_MILESTONES: [u64; 3] = [3, 5, 10];
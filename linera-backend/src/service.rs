@@ -3,16 +3,19 @@
 
 #![cfg_attr(target_arch = "wasm32", no_main)]
 
-mod state;
-
 use std::sync::Arc;
 
-use async_graphql::{EmptySubscription, Object, Request, Response, Schema};
-use linera_sdk::linera_base_types::ChainId;
-use rpg_game::{RpgGameOperation, PlayerState};
+use async_graphql::{EmptySubscription, Object, Request, Response, Schema, SimpleObject};
+use linera_sdk::linera_base_types::{AccountOwner, ChainId};
+use rpg_game::{
+    InventoryData, InventoryItem, ItemDefinition, QuestData, QuestDefinition, RpgGameOperation, Shop, ShopDefinition, TradeItem,
+};
 use linera_sdk::{linera_base_types::WithServiceAbi, views::View, Service, ServiceRuntime};
 
-use self::state::{PlayerData, RpgGameState, BattleRecord, GuildData};
+use rpg_game::state::{
+    BattleRecord, CurrencyTransfer, EffectiveStats, GuildData, LedgerEntry, LedgerEntryKind, PlayerData, PlayerTransferRequest,
+    RpgGameState, TradeOffer,
+};
 
 pub struct RpgGameService {
     state: Arc<RpgGameState>,
@@ -42,6 +45,7 @@ impl Service for RpgGameService {
         let schema = Schema::build(
             QueryRoot {
                 state: self.state.clone(),
+                runtime: self.runtime.clone(),
             },
             MutationRoot {
                 runtime: self.runtime.clone(),
@@ -53,49 +57,127 @@ impl Service for RpgGameService {
     }
 }
 
+#[derive(SimpleObject)]
+struct QuestCounts {
+    active: u64,
+    completed: u64,
+}
+
 struct QueryRoot {
     state: Arc<RpgGameState>,
+    runtime: Arc<ServiceRuntime<RpgGameService>>,
 }
 
 #[Object]
 impl QueryRoot {
+    async fn player_owner(&self, player_id: String) -> Option<AccountOwner> {
+        self.state.player_owners.get(&player_id).await
+            .expect("Failed to get player owner")
+    }
+
     async fn player_state(&self, player_id: String) -> Option<PlayerData> {
         self.state.player_states.get(&player_id).await
             .expect("Failed to get player state")
     }
 
-    async fn inventory(&self, player_id: String) -> Option<String> {
+    /// Player stats with the modifiers of equipped items applied
+    async fn effective_stats(&self, player_id: String) -> Option<EffectiveStats> {
+        self.state.effective_stats(&player_id).await
+    }
+
+    async fn balance(&self, player_id: String) -> u64 {
+        self.state.balances.get(&player_id).await
+            .expect("Failed to get balance")
+            .unwrap_or(0)
+    }
+
+    /// Every change to a player's balance on this chain, oldest first, optionally only of one kind
+    async fn currency_history(&self, player_id: String, kind: Option<LedgerEntryKind>) -> Vec<LedgerEntry> {
+        self.state.currency_history.get(&player_id).await
+            .expect("Failed to get currency history")
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|entry| kind.is_none_or(|kind| entry.kind == kind))
+            .collect()
+    }
+
+    async fn currency_transfer(&self, transfer_id: String) -> Option<CurrencyTransfer> {
+        self.state.currency_transfers.get(&transfer_id).await
+            .expect("Failed to get currency transfer")
+    }
+
+    async fn inventory(&self, player_id: String) -> Option<InventoryData> {
+        self.state.player_inventories.get(&player_id).await
+            .expect("Failed to get inventory")
+    }
+
+    /// The item a player keeps in `slot`, e.g. what is equipped in the weapon slot
+    async fn inventory_slot(&self, player_id: String, slot: String) -> Option<InventoryItem> {
         let inventory = self.state.player_inventories.get(&player_id).await
-            .expect("Failed to get inventory");
-
-        match inventory {
-            Some(inv) => {
-                // Convert inventory to JSON string
-                match serde_json::to_string(&inv.items) {
-                    Ok(json_str) => Some(json_str),
-                    Err(_) => None,
-                }
-            },
-            None => None,
-        }
+            .expect("Failed to get inventory")?;
+        inventory.item_in_slot(&slot).cloned()
+    }
+
+    async fn item_definition(&self, item_id: String) -> Option<ItemDefinition> {
+        self.state.item_catalogue.get(&item_id).await
+            .expect("Failed to get item definition")
     }
 
-    async fn quests(&self, player_id: String) -> Option<String> {
+    /// Every item players can hold on this chain
+    async fn item_catalogue(&self) -> Vec<ItemDefinition> {
+        let mut definitions = Vec::new();
+        self.state.item_catalogue
+            .for_each_index_value(|_item_id, definition| {
+                definitions.push(definition.into_owned());
+                Ok(())
+            })
+            .await
+            .expect("Failed to list item catalogue");
+        definitions
+    }
+
+    /// A player's quests, optionally only the completed (`true`) or active (`false`) ones
+    async fn quests(&self, player_id: String, completed: Option<bool>) -> Option<Vec<QuestData>> {
         let quests = self.state.player_quests.get(&player_id).await
-            .expect("Failed to get quests");
-
-        match quests {
-            Some(quest_list) => {
-                // Convert quests to JSON string
-                match serde_json::to_string(&quest_list) {
-                    Ok(json_str) => Some(json_str),
-                    Err(_) => None,
-                }
-            },
-            None => None,
+            .expect("Failed to get quests")?;
+        Some(
+            quests
+                .into_iter()
+                .filter(|quest| completed.is_none_or(|completed| quest.completed == completed))
+                .collect(),
+        )
+    }
+
+    async fn quest(&self, player_id: String, quest_id: String) -> Option<QuestData> {
+        let quests = self.state.player_quests.get(&player_id).await
+            .expect("Failed to get quests")?;
+        quests.into_iter().find(|quest| quest.id == quest_id)
+    }
+
+    async fn quest_counts(&self, player_id: String) -> QuestCounts {
+        let quests = self.state.player_quests.get(&player_id).await
+            .expect("Failed to get quests")
+            .unwrap_or_default();
+        let completed = quests.iter().filter(|quest| quest.completed).count() as u64;
+        QuestCounts {
+            active: quests.len() as u64 - completed,
+            completed,
         }
     }
 
+    /// Quests defined on this chain, with the reward each pays on completion
+    async fn quest_catalogue(&self) -> Vec<QuestDefinition> {
+        let mut definitions = Vec::new();
+        self.state.quest_catalogue
+            .for_each_index_value(|_quest_id, definition| {
+                definitions.push(definition.into_owned());
+                Ok(())
+            })
+            .await
+            .expect("Failed to list quest catalogue");
+        definitions
+    }
+
     async fn battle_record(&self, battle_id: String) -> Option<BattleRecord> {
         self.state.battle_records.get(&battle_id).await
             .expect("Failed to get battle record")
@@ -116,6 +198,58 @@ impl QueryRoot {
             .expect("Failed to get player guild")
     }
 
+    /// Shops of this region with their current stock, optionally only the ones trading `item_id`
+    async fn shops(&self, item_id: Option<String>) -> Vec<Shop> {
+        let now = self.runtime.system_time().micros();
+        let mut shops = Vec::new();
+        self.state.shops
+            .for_each_index_value(|_shop_id, shop| {
+                let mut shop = shop.into_owned();
+                shop.restock(now);
+                shops.push(shop);
+                Ok(())
+            })
+            .await
+            .expect("Failed to list shops");
+        if let Some(item_id) = item_id {
+            shops.retain(|shop| shop.items.iter().any(|item| item.listing.item_id == item_id));
+        }
+        shops
+    }
+
+    async fn shop(&self, shop_id: String) -> Option<Shop> {
+        let mut shop = self.state.shops.get(&shop_id).await
+            .expect("Failed to get shop")?;
+        shop.restock(self.runtime.system_time().micros());
+        Some(shop)
+    }
+
+    async fn trade(&self, trade_id: String) -> Option<TradeOffer> {
+        self.state.trades.get(&trade_id).await
+            .expect("Failed to get trade")
+    }
+
+    /// Trades a player of this chain takes part in, optionally only the open (`true`) or finished (`false`) ones
+    async fn player_trades(&self, player_id: String, open: Option<bool>) -> Vec<TradeOffer> {
+        let trade_ids = self.state.player_trades.get(&player_id).await
+            .expect("Failed to get player trades")
+            .unwrap_or_default();
+        let mut trades = Vec::new();
+        for trade_id in trade_ids {
+            let trade = self.state.trades.get(&trade_id).await
+                .expect("Failed to get trade");
+            if let Some(trade) = trade.filter(|trade| open.is_none_or(|open| trade.is_open() == open)) {
+                trades.push(trade);
+            }
+        }
+        trades
+    }
+
+    async fn transfer_request(&self, player_id: String) -> Option<PlayerTransferRequest> {
+        self.state.player_transfer_requests.get(&player_id).await
+            .expect("Failed to get player transfer request")
+    }
+
     async fn world_region(&self) -> String {
         self.state.world_region.get().clone()
     }
@@ -126,7 +260,24 @@ struct MutationRoot {
 }
 
 #[Object]
+#[allow(clippy::too_many_arguments)]
 impl MutationRoot {
+    async fn register_player(&self, player_id: String) -> [u8; 0] {
+        let operation = RpgGameOperation::RegisterPlayer { player_id };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn transfer_player_ownership(
+        &self,
+        player_id: String,
+        new_owner: AccountOwner,
+    ) -> [u8; 0] {
+        let operation = RpgGameOperation::TransferPlayerOwnership { player_id, new_owner };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
     async fn save_player_state(
         &self,
         player_id: String,
@@ -154,12 +305,177 @@ impl MutationRoot {
         []
     }
 
+    async fn add_experience(&self, player_id: String, amount: u64) -> [u8; 0] {
+        let operation = RpgGameOperation::AddExperience { player_id, amount };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn credit_currency(&self, player_id: String, amount: u64, reason: String) -> [u8; 0] {
+        let operation = RpgGameOperation::CreditCurrency { player_id, amount, reason };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn debit_currency(&self, player_id: String, amount: u64, reason: String) -> [u8; 0] {
+        let operation = RpgGameOperation::DebitCurrency { player_id, amount, reason };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn transfer_currency(
+        &self,
+        player_id: String,
+        recipient_id: String,
+        recipient_chain: ChainId,
+        amount: u64,
+    ) -> [u8; 0] {
+        let operation = RpgGameOperation::TransferCurrency {
+            player_id,
+            recipient_id,
+            recipient_chain,
+            amount,
+        };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn define_shop(&self, shop: ShopDefinition) -> [u8; 0] {
+        let operation = RpgGameOperation::DefineShop { shop };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn buy_item(&self, player_id: String, shop_id: String, item_id: String, quantity: Option<u64>) -> [u8; 0] {
+        let operation = RpgGameOperation::BuyItem {
+            player_id,
+            shop_id,
+            item_id,
+            quantity: quantity.unwrap_or(1),
+        };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn sell_item(&self, player_id: String, shop_id: String, slot: String, quantity: Option<u64>) -> [u8; 0] {
+        let operation = RpgGameOperation::SellItem {
+            player_id,
+            shop_id,
+            slot,
+            quantity: quantity.unwrap_or(1),
+        };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
     async fn save_inventory(
         &self,
         player_id: String,
-        inventory: String,  // JSON string
+        items: Vec<InventoryItem>,
+    ) -> [u8; 0] {
+        let operation = RpgGameOperation::SaveInventory { player_id, items };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn define_quest(&self, definition: QuestDefinition) -> [u8; 0] {
+        let operation = RpgGameOperation::DefineQuest { definition };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn complete_quest(&self, player_id: String, quest_id: String) -> [u8; 0] {
+        let operation = RpgGameOperation::CompleteQuest { player_id, quest_id };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn define_item(&self, definition: ItemDefinition) -> [u8; 0] {
+        let operation = RpgGameOperation::DefineItem { definition };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn add_item(
+        &self,
+        player_id: String,
+        item_id: String,
+        quantity: Option<u64>,
+        slot: Option<String>,
+    ) -> [u8; 0] {
+        let operation = RpgGameOperation::AddItem {
+            player_id,
+            item_id,
+            quantity: quantity.unwrap_or(1),
+            slot,
+        };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn remove_item(&self, player_id: String, slot: String) -> [u8; 0] {
+        let operation = RpgGameOperation::RemoveItem { player_id, slot };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn move_item(&self, player_id: String, from_slot: String, to_slot: String) -> [u8; 0] {
+        let operation = RpgGameOperation::MoveItem { player_id, from_slot, to_slot };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn equip_item(&self, player_id: String, from_slot: String, equip_slot: String) -> [u8; 0] {
+        let operation = RpgGameOperation::EquipItem { player_id, from_slot, equip_slot };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn unequip_item(&self, player_id: String, equip_slot: String, to_slot: Option<String>) -> [u8; 0] {
+        let operation = RpgGameOperation::UnequipItem { player_id, equip_slot, to_slot };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn propose_trade(
+        &self,
+        player_id: String,
+        counterparty_id: String,
+        counterparty_chain: ChainId,
+        offered: Vec<TradeItem>,
+        requested: Vec<TradeItem>,
     ) -> [u8; 0] {
-        let operation = RpgGameOperation::SaveInventory { player_id, inventory };
+        let operation = RpgGameOperation::ProposeTrade {
+            player_id,
+            counterparty_id,
+            counterparty_chain,
+            offered,
+            requested,
+        };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn accept_trade(&self, trade_id: String) -> [u8; 0] {
+        let operation = RpgGameOperation::AcceptTrade { trade_id };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn reject_trade(&self, trade_id: String) -> [u8; 0] {
+        let operation = RpgGameOperation::RejectTrade { trade_id };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn cancel_trade(&self, trade_id: String) -> [u8; 0] {
+        let operation = RpgGameOperation::CancelTrade { trade_id };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
+
+    async fn claim_trade_items(&self, trade_id: String) -> [u8; 0] {
+        let operation = RpgGameOperation::ClaimTradeItems { trade_id };
         self.runtime.schedule_operation(&operation);
         []
     }
@@ -167,7 +483,7 @@ impl MutationRoot {
     async fn save_quests(
         &self,
         player_id: String,
-        quests: String,  // JSON string
+        quests: Vec<QuestData>,
     ) -> [u8; 0] {
         let operation = RpgGameOperation::SaveQuests { player_id, quests };
         self.runtime.schedule_operation(&operation);
@@ -178,36 +494,10 @@ impl MutationRoot {
         &self,
         player_id: String,
         destination_chain: ChainId,
-        health: u64,
-        max_health: u64,
-        strength: u64,
-        wisdomness: u64,
-        benchpress: u64,
-        curl: u64,
-        experience: u64,
-        level: u64,
-        inventory: String,
-        quests: String,
-        auth_token: String,
     ) -> [u8; 0] {
-        let player_state = PlayerState {
-            health,
-            max_health,
-            strength,
-            wisdomness,
-            benchpress,
-            curl,
-            experience,
-            level,
-        };
-
         let operation = RpgGameOperation::TransferPlayer {
             player_id,
             destination_chain,
-            player_state,
-            inventory,
-            quests,
-            auth_token,
         };
         self.runtime.schedule_operation(&operation);
         []
@@ -250,4 +540,10 @@ impl MutationRoot {
         self.runtime.schedule_operation(&operation);
         []
     }
+
+    async fn reward_battle(&self, battle_id: String, amount: u64) -> [u8; 0] {
+        let operation = RpgGameOperation::RewardBattle { battle_id, amount };
+        self.runtime.schedule_operation(&operation);
+        []
+    }
 }
\ No newline at end of file
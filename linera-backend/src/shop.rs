@@ -0,0 +1,114 @@
+// RPG Game Shops
+// SPDX-License-Identifier: MIT
+
+use serde::{Deserialize, Serialize};
+
+use crate::RpgGameError;
+
+/// An item a shop trades, at the prices set by the region's admins
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, async_graphql::SimpleObject, async_graphql::InputObject)]
+#[graphql(input_name = "ShopListingInput")]
+pub struct ShopListing {
+    pub item_id: String,
+    /// Price of one unit
+    pub price: u64,
+    /// What the shop pays for one unit a player sells; `None` if it does not buy the item
+    pub buyback_price: Option<u64>,
+    /// Most units the shop holds; restocking and buying back stop there
+    pub max_stock: u64,
+    /// Units added every `restock_interval_micros`
+    pub restock_quantity: u64,
+    /// Time between two restocks; 0 if the item is never restocked
+    pub restock_interval_micros: u64,
+}
+
+/// A vendor shop of a world region
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, async_graphql::SimpleObject, async_graphql::InputObject)]
+#[graphql(input_name = "ShopDefinitionInput")]
+pub struct ShopDefinition {
+    pub id: String,
+    pub name: String,
+    pub listings: Vec<ShopListing>,
+}
+
+impl ShopDefinition {
+    /// Checks that the listings are consistent; the items themselves are checked against the catalogue separately.
+    pub fn validate(&self) -> Result<(), RpgGameError> {
+        for (index, listing) in self.listings.iter().enumerate() {
+            if self.listings[..index].iter().any(|other| other.item_id == listing.item_id) {
+                return Err(RpgGameError::InvalidShop(format!("item {} is listed twice", listing.item_id)));
+            }
+            // Otherwise players could buy and sell back at a profit
+            if listing.buyback_price.is_some_and(|buyback_price| buyback_price > listing.price) {
+                return Err(RpgGameError::InvalidShop(format!("item {} is bought back above its price", listing.item_id)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A shop listing with its current stock
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, async_graphql::SimpleObject)]
+pub struct ShopItem {
+    pub listing: ShopListing,
+    pub stock: u64,
+    /// When the last restock was due
+    pub last_restock: u64,
+}
+
+impl ShopItem {
+    /// Adds the restocks due by `now`, up to the listing's maximum stock.
+    pub fn restock(&mut self, now: u64) {
+        let interval = self.listing.restock_interval_micros;
+        if interval == 0 {
+            return;
+        }
+        let restocks = now.saturating_sub(self.last_restock) / interval;
+        if restocks == 0 {
+            return;
+        }
+        let added = restocks.saturating_mul(self.listing.restock_quantity);
+        self.stock = self.stock.saturating_add(added).min(self.listing.max_stock);
+        // Keep the restock schedule, even when the shop was already full
+        self.last_restock = self.last_restock.saturating_add(restocks.saturating_mul(interval));
+    }
+}
+
+/// A vendor shop of this region, as stored on chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, async_graphql::SimpleObject)]
+pub struct Shop {
+    pub id: String,
+    pub name: String,
+    pub items: Vec<ShopItem>,
+}
+
+impl Shop {
+    /// Opens a shop from its definition, with every listing fully stocked at `now`.
+    pub fn new(definition: ShopDefinition, now: u64) -> Self {
+        let items = definition.listings
+            .into_iter()
+            .map(|listing| ShopItem {
+                stock: listing.max_stock,
+                listing,
+                last_restock: now,
+            })
+            .collect();
+        Shop {
+            id: definition.id,
+            name: definition.name,
+            items,
+        }
+    }
+
+    /// Adds the restocks due by `now` to every listing.
+    pub fn restock(&mut self, now: u64) {
+        for item in &mut self.items {
+            item.restock(now);
+        }
+    }
+
+    /// Returns the listing of `item_id`, if the shop trades it.
+    pub fn item_mut(&mut self, item_id: &str) -> Option<&mut ShopItem> {
+        self.items.iter_mut().find(|item| item.listing.item_id == item_id)
+    }
+}
@@ -1,8 +1,12 @@
 // RPG Game State
 // SPDX-License-Identifier: MIT
 
-use linera_sdk::views::{MapView, RegisterView, RootView, ViewStorageContext};
-use linera_sdk::linera_base_types::ChainId;
+use linera_sdk::views::{MapView, RegisterView, RootView, SetView, ViewStorageContext};
+use linera_sdk::linera_base_types::{AccountOwner, ChainId};
+use crate::{
+    progression, InventoryData, InventoryItem, ItemDefinition, QuestData, QuestDefinition, Shop, StatModifiers, TradeItem, TradeStatus,
+    TransferStatus,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, async_graphql::SimpleObject)]
@@ -17,25 +21,48 @@ pub struct PlayerData {
     pub level: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, async_graphql::SimpleObject)]
-pub struct InventoryData {
-    pub items: Vec<InventoryItem>,
-}
+impl PlayerData {
+    /// Adds `amount` experience and applies every level-up it earns.
+    ///
+    /// Returns the number of levels gained.
+    pub fn add_experience(&mut self, amount: u64) -> u64 {
+        self.experience = self.experience.saturating_add(amount);
+        let new_level = progression::level_for_experience(self.level, self.experience);
+        let levels_gained = new_level.saturating_sub(self.level.max(1));
 
-#[derive(Debug, Clone, Serialize, Deserialize, async_graphql::SimpleObject)]
-pub struct InventoryItem {
-    pub slot: String,
-    pub item_id: String,
-    pub params: serde_json::Value,  // Flexible params structure
+        self.level = new_level;
+        self.strength = self.strength.saturating_add(levels_gained.saturating_mul(progression::STRENGTH_PER_LEVEL));
+        self.wisdomness = self.wisdomness.saturating_add(levels_gained.saturating_mul(progression::WISDOMNESS_PER_LEVEL));
+        self.benchpress = self.benchpress.saturating_add(levels_gained.saturating_mul(progression::BENCHPRESS_PER_LEVEL));
+        self.curl = self.curl.saturating_add(levels_gained.saturating_mul(progression::CURL_PER_LEVEL));
+        levels_gained
+    }
+
+    /// Returns the stats of this player with `equipment` modifiers applied.
+    pub fn effective_stats(&self, equipment: &StatModifiers) -> EffectiveStats {
+        let max_health = StatModifiers::apply(self.max_health, equipment.max_health);
+        EffectiveStats {
+            health: self.health.min(max_health),
+            max_health,
+            strength: StatModifiers::apply(self.strength, equipment.strength),
+            wisdomness: StatModifiers::apply(self.wisdomness, equipment.wisdomness),
+            benchpress: StatModifiers::apply(self.benchpress, equipment.benchpress),
+            curl: StatModifiers::apply(self.curl, equipment.curl),
+            level: self.level,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, async_graphql::SimpleObject)]
-pub struct QuestData {
-    pub id: String,
-    pub title: String,
-    pub text: String,
-    pub completed: bool,
-    pub progress: u64,  // For quests that track progress (e.g., kill 10 monsters)
+/// A player's stats with the modifiers of their equipped items applied
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, async_graphql::SimpleObject)]
+pub struct EffectiveStats {
+    pub health: u64,
+    pub max_health: u64,
+    pub strength: u64,
+    pub wisdomness: u64,
+    pub benchpress: u64,
+    pub curl: u64,
+    pub level: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, async_graphql::SimpleObject)]
@@ -48,6 +75,8 @@ pub struct BattleRecord {
     pub damage_taken: u64,
     pub experience_gained: u64,
     pub timestamp: u64,
+    /// The player's effective stats when the battle was recorded, if they had saved stats
+    pub player_stats: Option<EffectiveStats>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, async_graphql::SimpleObject)]
@@ -64,14 +93,132 @@ pub struct PlayerTransferRequest {
     pub source_chain: ChainId,
     pub destination_chain: ChainId,
     pub player_id: String,
+    pub nonce: u64,
     pub auth_token: String,
     pub timestamp: u64,
+    pub status: TransferStatus,
+    /// Why the player could not move, once the transfer failed
+    pub failure_reason: Option<String>,
+    /// Currency the player carried along, refunded if the transfer fails
+    pub balance: u64,
+}
+
+/// What moved currency in or out of a player's balance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, async_graphql::Enum)]
+pub enum LedgerEntryKind {
+    /// Granted by an admin
+    Credit,
+    /// Taken by an admin
+    Debit,
+    /// Paid by another player
+    TransferIn,
+    /// Paid to another player
+    TransferOut,
+    /// A failed outgoing transfer was returned
+    Refund,
+    /// Paid for completing a quest of the catalogue
+    QuestReward,
+    /// Paid for a recorded battle
+    BattleReward,
+    /// Paid to a shop
+    Purchase,
+    /// Paid by a shop
+    Sale,
+    /// Brought along by the player arriving from another chain
+    ChainArrival,
+    /// Carried along by the player leaving for another chain
+    ChainDeparture,
+}
+
+impl LedgerEntryKind {
+    /// Whether entries of this kind add to the balance.
+    pub fn is_credit(self) -> bool {
+        !matches!(
+            self,
+            LedgerEntryKind::Debit | LedgerEntryKind::TransferOut | LedgerEntryKind::Purchase | LedgerEntryKind::ChainDeparture
+        )
+    }
+}
+
+/// A change to a player's currency balance, kept for auditing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, async_graphql::SimpleObject)]
+pub struct LedgerEntry {
+    pub kind: LedgerEntryKind,
+    pub amount: u64,
+    /// Balance right after this entry
+    pub balance: u64,
+    /// The other player of a transfer
+    pub counterparty: Option<String>,
+    /// The other chain of a cross-chain movement
+    pub counterparty_chain: Option<ChainId>,
+    /// Why the balance changed: the admin's reason, battle ID, quest ID or transfer ID
+    pub memo: String,
+    pub timestamp: u64,
+}
+
+impl LedgerEntry {
+    /// Returns an entry without counterparty; the balance and timestamp are set when it is posted.
+    pub fn new(kind: LedgerEntryKind, amount: u64, memo: String) -> Self {
+        LedgerEntry {
+            kind,
+            amount,
+            balance: 0,
+            counterparty: None,
+            counterparty_chain: None,
+            memo,
+            timestamp: 0,
+        }
+    }
+}
+
+/// An outgoing cross-chain currency transfer
+#[derive(Debug, Clone, Serialize, Deserialize, async_graphql::SimpleObject)]
+pub struct CurrencyTransfer {
+    pub transfer_id: String,
+    pub sender_id: String,
+    pub recipient_id: String,
+    pub recipient_chain: ChainId,
+    pub amount: u64,
+    pub timestamp: u64,
+    pub status: TransferStatus,
+    /// Why the currency was refunded, once the transfer failed
+    pub failure_reason: Option<String>,
+}
+
+/// A trade between two players, as seen by the chain of one of them
+#[derive(Debug, Clone, Serialize, Deserialize, async_graphql::SimpleObject)]
+pub struct TradeOffer {
+    pub trade_id: String,
+    pub proposer_id: String,
+    pub proposer_chain: ChainId,
+    pub counterparty_id: String,
+    pub counterparty_chain: ChainId,
+    /// Items the proposer gives
+    pub offered: Vec<TradeItem>,
+    /// Items the proposer asks for in return
+    pub requested: Vec<TradeItem>,
+    /// Items this chain holds for the trade: the offered items while it is pending,
+    /// then any items that did not fit in the receiving player's bag
+    pub escrow: Vec<InventoryItem>,
+    pub status: TradeStatus,
+    pub created_at: u64,
+    /// Why the escrow went back to the proposer, if the trade failed
+    pub failure_reason: Option<String>,
+}
+
+impl TradeOffer {
+    /// Whether the trade still needs something from a player of this chain: an answer, a settlement or a claim.
+    pub fn is_open(&self) -> bool {
+        matches!(self.status, TradeStatus::Pending | TradeStatus::Accepted) || !self.escrow.is_empty()
+    }
 }
 
 /// The main application state.
 #[derive(RootView)]
 #[view(context = ViewStorageContext)]
 pub struct RpgGameState {
+    /// Map of player IDs to the account that owns them
+    pub player_owners: MapView<String, AccountOwner>,
     /// Map of player IDs to their state
     pub player_states: MapView<String, PlayerData>,
     /// Map of player IDs to their inventory
@@ -84,12 +231,70 @@ pub struct RpgGameState {
     pub guilds: MapView<String, GuildData>,
     /// Guild membership map (player_id -> guild_id)
     pub player_guilds: MapView<String, String>,
+    /// Consecutive battle wins of each player
+    pub player_win_streaks: MapView<String, u64>,
     /// Battle records organized by player
     pub player_battles: MapView<String, Vec<String>>,  // List of battle IDs for each player
     /// Guild membership requests
     pub guild_join_requests: MapView<String, Vec<String>>,  // guild_id -> [player_ids]
-    /// Player transfer requests
-    pub player_transfer_requests: MapView<String, PlayerTransferRequest>,  // transfer_id -> request
+    /// Latest outgoing transfer of each player
+    pub player_transfer_requests: MapView<String, PlayerTransferRequest>,  // player_id -> request
+    /// Nonce used for the next outgoing player transfer token
+    pub next_transfer_nonce: RegisterView<u64>,
+    /// Transfer tokens already accepted by this chain, to refuse replays
+    pub consumed_transfer_tokens: MapView<String, u64>,  // token -> timestamp
+    /// Trades involving a player of this chain
+    pub trades: MapView<String, TradeOffer>,
+    /// Trades organized by player
+    pub player_trades: MapView<String, Vec<String>>,  // List of trade IDs for each player
+    /// Nonce used for the next trade proposed on this chain
+    pub next_trade_nonce: RegisterView<u64>,
+    /// Currency balance of each player
+    pub balances: MapView<String, u64>,
+    /// Every change to each player's balance, oldest first
+    pub currency_history: MapView<String, Vec<LedgerEntry>>,
+    /// Quests defined on this chain, which pay their reward on completion
+    pub quest_catalogue: MapView<String, QuestDefinition>,
+    /// Catalogue quests completed through `CompleteQuest`, as (player ID, quest ID)
+    pub completed_quests: SetView<(String, String)>,
+    /// Battles that already paid their reward
+    pub rewarded_battles: SetView<String>,
+    /// Outgoing cross-chain currency transfers
+    pub currency_transfers: MapView<String, CurrencyTransfer>,
+    /// Nonce used for the next outgoing currency transfer
+    pub next_currency_transfer_nonce: RegisterView<u64>,
+    /// Items players can hold on this chain
+    pub item_catalogue: MapView<String, ItemDefinition>,
+    /// Vendor shops of this region
+    pub shops: MapView<String, Shop>,
+    /// Owners allowed to manage the item catalogue, balances and shops
+    pub admins: SetView<AccountOwner>,
     /// World region identifier for this chain
     pub world_region: RegisterView<String>,
-}
\ No newline at end of file
+}
+
+impl RpgGameState {
+    /// Returns the combined stat modifiers of the items `player_id` has equipped.
+    ///
+    /// Equipped items missing from the catalogue grant nothing.
+    pub async fn equipment_modifiers(&self, player_id: &str) -> StatModifiers {
+        let mut modifiers = StatModifiers::default();
+        let Some(inventory) = self.player_inventories.get(player_id).await.expect("Failed to get inventory") else {
+            return modifiers;
+        };
+        for item in inventory.equipped_items() {
+            if let Some(definition) = self.item_catalogue.get(&item.item_id).await.expect("Failed to get item definition") {
+                modifiers.add(&definition.stat_modifiers);
+            }
+        }
+        modifiers
+    }
+
+    /// Returns the effective stats of `player_id`, or `None` if the player has no saved stats.
+    pub async fn effective_stats(&self, player_id: &str) -> Option<EffectiveStats> {
+        let player_data = self.player_states.get(player_id).await
+            .expect("Failed to get player state")?;
+        let modifiers = self.equipment_modifiers(player_id).await;
+        Some(player_data.effective_stats(&modifiers))
+    }
+}
@@ -1,29 +1,211 @@
 #[cfg(test)]
 mod tests {
-    use super::*;
-    use linera_sdk::{test::TestBuilder, BaseLayer, ChainId};
+    use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+    use linera_sdk::{
+        linera_base_types::{AccountOwner, ApplicationId, ChainId, ContractAbi, CryptoHash, Timestamp},
+        views::{RootView, View},
+        Contract, ContractRuntime,
+    };
+    use rpg_game::{
+        state::{LedgerEntryKind, RpgGameState},
+        HubOperation, RpgGameAbi, RpgGameError, RpgGameMessage, RpgGameOperation, RpgGameResponse, RpgHubAbi,
+    };
     use serde_json::json;
-    use std::collections::BTreeMap;
+
+    use crate::RpgGameContract;
+
+    /// Signer of every block and message in these tests, unless a test says otherwise.
+    const OWNER: AccountOwner = AccountOwner::Address20([1; 20]);
+
+    fn chain(index: u32) -> ChainId {
+        ChainId(CryptoHash::test_hash(format!("chain{}", index)))
+    }
+
+    fn parameters(world_region: &str) -> rpg_game::Parameters {
+        rpg_game::Parameters {
+            world_region: world_region.to_string(),
+            hub_application_id: None,
+            hub_chain_id: None,
+            save_rules: rpg_game::SaveRules::default(),
+            admins: Vec::new(),
+        }
+    }
+
+    /// A message sent to another chain and not executed yet.
+    struct Delivery {
+        origin: ChainId,
+        signer: Option<AccountOwner>,
+        message: RpgGameMessage,
+    }
+
+    /// The game application on several chains of the SDK's mock runtime, with the messages in flight between them.
+    ///
+    /// Each operation or message runs as its own block: its state is saved afterwards and the messages it sent
+    /// wait in the destination's inbox until [`Network::process_inbox`]. Calls to the hub are recorded in
+    /// `hub_calls` instead of being executed.
+    struct Network {
+        contracts: BTreeMap<ChainId, RpgGameContract>,
+        inboxes: BTreeMap<ChainId, Vec<Delivery>>,
+        hub_calls: Rc<RefCell<Vec<HubOperation>>>,
+        signer: AccountOwner,
+    }
+
+    impl Default for Network {
+        fn default() -> Self {
+            Network {
+                contracts: BTreeMap::new(),
+                inboxes: BTreeMap::new(),
+                hub_calls: Rc::default(),
+                signer: OWNER,
+            }
+        }
+    }
+
+    impl Network {
+        /// Creates the application on `chain_id`, signed by the current signer.
+        async fn create_application(&mut self, chain_id: ChainId, parameters: rpg_game::Parameters) {
+            let world_region = parameters.world_region.clone();
+            let mut contract = self.load(chain_id, chain_id, parameters).await;
+            contract.runtime.set_authenticated_signer(self.signer);
+            contract.instantiate(world_region).await;
+            contract.state.save().await.expect("Failed to save state");
+            self.contracts.insert(chain_id, contract);
+        }
+
+        /// Opens the application created on `creator_chain_id` on another chain as well.
+        ///
+        /// Only the creator chain runs `instantiate`; the other chains start from an empty state.
+        async fn add_chain(&mut self, chain_id: ChainId, creator_chain_id: ChainId) {
+            let parameters = self.contracts.get_mut(&creator_chain_id)
+                .expect("The application was not created on the creator chain")
+                .runtime.application_parameters();
+            let contract = self.load(chain_id, creator_chain_id, parameters).await;
+            self.contracts.insert(chain_id, contract);
+        }
+
+        async fn load(&mut self, chain_id: ChainId, creator_chain_id: ChainId, parameters: rpg_game::Parameters) -> RpgGameContract {
+            let hub_calls = self.hub_calls.clone();
+            let runtime = ContractRuntime::new()
+                .with_application_parameters(parameters)
+                .with_application_id(ApplicationId::new(CryptoHash::test_hash("rpg_game")).with_abi())
+                .with_application_creator_chain_id(creator_chain_id)
+                .with_chain_id(chain_id)
+                .with_system_time(Timestamp::from(0))
+                .with_call_application_handler(move |_authenticated, _application_id, operation| {
+                    hub_calls.borrow_mut().push(RpgHubAbi::deserialize_operation(operation).unwrap());
+                    RpgHubAbi::serialize_response(Ok(())).unwrap()
+                });
+            RpgGameContract::load(runtime).await
+        }
+
+        /// Executes `operation` in a block of `chain_id` signed by the current signer.
+        async fn execute(&mut self, chain_id: ChainId, operation: &RpgGameOperation) -> Result<RpgGameResponse, RpgGameError> {
+            let contract = self.contracts.get_mut(&chain_id).expect("The application does not run on this chain");
+            contract.runtime
+                .set_authenticated_signer(self.signer)
+                .set_authenticated_caller_id(None)
+                .set_message_origin_chain_id(None)
+                .set_message_is_bouncing(None);
+            let operation = RpgGameAbi::deserialize_operation(RpgGameAbi::serialize_operation(operation).unwrap()).unwrap();
+            let response = contract.execute_operation(operation).await;
+            self.commit(chain_id).await;
+            response
+        }
+
+        /// Executes the messages waiting for `chain_id`, in the order they were sent.
+        async fn process_inbox(&mut self, chain_id: ChainId) {
+            for delivery in self.inboxes.remove(&chain_id).unwrap_or_default() {
+                let contract = self.contracts.get_mut(&chain_id).expect("The application does not run on this chain");
+                contract.runtime
+                    .set_authenticated_signer(delivery.signer)
+                    .set_authenticated_caller_id(None)
+                    .set_message_origin_chain_id(delivery.origin)
+                    .set_message_is_bouncing(false);
+                contract.execute_message(delivery.message).await;
+                self.commit(chain_id).await;
+            }
+        }
+
+        /// Saves the state of `chain_id` and moves the messages it sent to their inboxes.
+        async fn commit(&mut self, chain_id: ChainId) {
+            let contract = self.contracts.get_mut(&chain_id).expect("The application does not run on this chain");
+            contract.state.save().await.expect("Failed to save state");
+            let signer = contract.runtime.authenticated_signer();
+            let requests = std::mem::take(&mut *contract.runtime.created_send_message_requests());
+            for request in requests {
+                self.inboxes.entry(request.destination).or_default().push(Delivery {
+                    origin: chain_id,
+                    signer: if request.authenticated { signer } else { None },
+                    message: request.message,
+                });
+            }
+        }
+
+        /// Loads the saved state of `chain_id`.
+        async fn view(&self, chain_id: ChainId) -> RpgGameState {
+            let context = self.contracts[&chain_id].runtime.root_view_storage_context();
+            RpgGameState::load(context).await.expect("Failed to load state")
+        }
+    }
+
+    fn item(slot: &str, item_id: &str) -> rpg_game::InventoryItem {
+        rpg_game::InventoryItem {
+            slot: slot.to_string(),
+            item_id: item_id.to_string(),
+            quantity: 1,
+            params: rpg_game::ItemParams::default(),
+        }
+    }
+
+    /// Grants one unit of `item_id` to `player_id` in the first free bag slot.
+    fn grant(player_id: &str, item_id: &str) -> RpgGameOperation {
+        RpgGameOperation::AddItem {
+            player_id: player_id.to_string(),
+            item_id: item_id.to_string(),
+            quantity: 1,
+            slot: None,
+        }
+    }
+
+    fn item_definition(id: &str, slot_type: Option<rpg_game::EquipmentSlot>) -> rpg_game::ItemDefinition {
+        rpg_game::ItemDefinition {
+            id: id.to_string(),
+            name: id.replace('_', " "),
+            slot_type,
+            stat_modifiers: rpg_game::StatModifiers::default(),
+            rarity: rpg_game::ItemRarity::Common,
+            stack_size: 1,
+            tradable: true,
+            params: rpg_game::ItemParams::default(),
+        }
+    }
+
+    fn quest(id: &str, title: &str, completed: bool, progress: u64) -> rpg_game::QuestData {
+        rpg_game::QuestData {
+            id: id.to_string(),
+            title: title.to_string(),
+            text: format!("Complete {}", title),
+            completed,
+            progress,
+        }
+    }
+
+    /// Returns why an operation was refused; a refused operation keeps none of its changes.
+    fn refusal(result: Result<RpgGameResponse, RpgGameError>) -> String {
+        match result {
+            Ok(response) => panic!("operation succeeded with {:?}", response),
+            Err(error) => error.to_string(),
+        }
+    }
 
     #[tokio::test]
     async fn test_rpg_game_lifecycle() {
-        // Create a test environment with two chains
-        let (mut builder, _committee) = TestBuilder::new()
-            .with_base_layer(BaseLayer::Simulator)
-            .with_nb_chains(2)
-            .build();
-        
-        let chain1 = ChainId::root(0);
-        let chain2 = ChainId::root(1);
+        let mut network = Network::default();
 
-        // Deploy the application to both chains
-        let app = builder.publish_and_create::<rpg_game::RpgGameAbi, String, String, _>(
-            chain1,
-            "world1".to_string(),
-            "world1".to_string(),
-            &(),
-            &mut BTreeMap::default(),
-        ).await.unwrap();
+        let chain1 = chain(0);
+
+        network.create_application(chain1, parameters("world1")).await;
 
         // Test saving player state
         let player_id = "player1".to_string();
@@ -31,24 +213,27 @@ mod tests {
             health: 100,
             max_health: 100,
             strength: 10,
-            wisdomness: 8,
+            wisdomness: 5,
             benchpress: 5,
-            curl: 3,
+            curl: 5,
             experience: 0,
             level: 1,
         };
         
-        let inventory = json!([
-            {"slot": "weapon", "item_id": "sword1", "params": {}},
-            {"slot": "armor", "item_id": "shield1", "params": {}},
-        ]).to_string();
+        let inventory = vec![
+            item("inventory-equip-1", "sword1"),
+            item("inventory-equip-2", "shield1"),
+        ];
         
-        let quests = json!([
-            {"id": "quest1", "title": "First Quest", "text": "Complete the first quest", "completed": false, "progress": 0},
-        ]).to_string();
+        let quests = vec![quest("quest1", "First Quest", false, 0)];
+
+        // Register the player to the chain owner
+        network.execute(chain1, &RpgGameOperation::RegisterPlayer {
+            player_id: player_id.clone(),
+        }).await.unwrap();
 
         // Save player data
-        builder.call_application(chain1, app, &rpg_game::RpgGameOperation::SavePlayerState {
+        network.execute(chain1, &RpgGameOperation::SavePlayerState {
             player_id: player_id.clone(),
             health: player_data.health,
             max_health: player_data.max_health,
@@ -60,26 +245,32 @@ mod tests {
             level: player_data.level,
         }).await.unwrap();
 
-        // Save inventory
-        builder.call_application(chain1, app, &rpg_game::RpgGameOperation::SaveInventory {
+        // Grant catalogue items, then save the inventory with them equipped
+        for (item_id, slot_type) in [("sword1", rpg_game::EquipmentSlot::Weapon), ("shield1", rpg_game::EquipmentSlot::Offhand)] {
+            network.execute(chain1, &RpgGameOperation::DefineItem {
+                definition: item_definition(item_id, Some(slot_type)),
+            }).await.unwrap();
+            network.execute(chain1, &grant(&player_id, item_id)).await.unwrap();
+        }
+        network.execute(chain1, &RpgGameOperation::SaveInventory {
             player_id: player_id.clone(),
-            inventory: inventory.clone(),
+            items: inventory.clone(),
         }).await.unwrap();
 
         // Save quests
-        builder.call_application(chain1, app, &rpg_game::RpgGameOperation::SaveQuests {
+        network.execute(chain1, &RpgGameOperation::SaveQuests {
             player_id: player_id.clone(),
             quests: quests.clone(),
         }).await.unwrap();
 
         // Verify data was saved correctly
-        let state = builder.view(chain1, app).await.unwrap();
+        let state = network.view(chain1).await;
         assert_eq!(state.player_states.get(&player_id).await.unwrap().unwrap().health, 100);
         assert_eq!(state.player_inventories.get(&player_id).await.unwrap().unwrap().items.len(), 2);
         assert_eq!(state.player_quests.get(&player_id).await.unwrap().unwrap().len(), 1);
 
         // Record a battle
-        builder.call_application(chain1, app, &rpg_game::RpgGameOperation::RecordBattle {
+        network.execute(chain1, &RpgGameOperation::RecordBattle {
             battle_id: "battle1".to_string(),
             player_id: player_id.clone(),
             opponent: "goblin".to_string(),
@@ -90,37 +281,22 @@ mod tests {
         }).await.unwrap();
 
         // Verify battle was recorded
-        let state = builder.view(chain1, app).await.unwrap();
+        let state = network.view(chain1).await;
         assert_eq!(state.battle_records.get(&"battle1".to_string()).await.unwrap().unwrap().result, 2);
     }
 
     #[tokio::test]
     async fn test_cross_chain_player_transfer() {
         // Test cross-chain player transfer functionality
-        let (mut builder, _committee) = TestBuilder::new()
-            .with_base_layer(BaseLayer::Simulator)
-            .with_nb_chains(2)
-            .build();
+        let mut network = Network::default();
         
-        let chain1 = ChainId::root(0);
-        let chain2 = ChainId::root(1);
+        let chain1 = chain(0);
+        let chain2 = chain(1);
 
         // Deploy the application to both chains with different world regions
-        let app1 = builder.publish_and_create::<rpg_game::RpgGameAbi, String, String, _>(
-            chain1,
-            "world1".to_string(),
-            "world1".to_string(),
-            &(),
-            &mut BTreeMap::default(),
-        ).await.unwrap();
-
-        let app2 = builder.publish_and_create::<rpg_game::RpgGameAbi, String, String, _>(
-            chain2,
-            "world2".to_string(),
-            "world2".to_string(),
-            &(),
-            &mut BTreeMap::default(),
-        ).await.unwrap();
+        network.create_application(chain1, parameters("world1")).await;
+
+        network.create_application(chain2, parameters("world2")).await;
 
         let player_id = "transferring_player".to_string();
         
@@ -128,117 +304,164 @@ mod tests {
         let player_data = rpg_game::PlayerState {
             health: 80,
             max_health: 100,
-            strength: 15,
-            wisdomness: 12,
-            benchpress: 7,
-            curl: 5,
+            strength: 11,
+            wisdomness: 6,
+            benchpress: 6,
+            curl: 7,
             experience: 150,
             level: 2,
         };
         
-        let inventory = json!([
-            {"slot": "weapon", "item_id": "magic_sword", "params": {}},
-            {"slot": "armor", "item_id": "magic_armor", "params": {}},
-        ]).to_string();
+        let inventory = vec![
+            item("inventory-equip-1", "magic_sword"),
+            item("inventory-1", "magic_armor"),
+        ];
         
-        let quests = json!([
-            {"id": "quest1", "title": "Ongoing Quest", "text": "Continue this quest", "completed": false, "progress": 5},
-        ]).to_string();
+        let quests = vec![quest("quest1", "Ongoing Quest", false, 5)];
+
+        // Register and save player data on chain1
+        network.execute(chain1, &RpgGameOperation::RegisterPlayer {
+            player_id: player_id.clone(),
+        }).await.unwrap();
 
-        // Save player data on chain1
-        builder.call_application(chain1, app1, &rpg_game::RpgGameOperation::SavePlayerState {
+        // New characters start at level 1; the chain applies the level-up to reach player_data
+        network.execute(chain1, &RpgGameOperation::SavePlayerState {
             player_id: player_id.clone(),
             health: player_data.health,
             max_health: player_data.max_health,
-            strength: player_data.strength,
-            wisdomness: player_data.wisdomness,
-            benchpress: player_data.benchpress,
-            curl: player_data.curl,
-            experience: player_data.experience,
-            level: player_data.level,
+            strength: player_data.strength - 1,
+            wisdomness: player_data.wisdomness - 1,
+            benchpress: player_data.benchpress - 1,
+            curl: player_data.curl - 2,
+            experience: 0,
+            level: 1,
         }).await.unwrap();
 
-        builder.call_application(chain1, app1, &rpg_game::RpgGameOperation::SaveInventory {
+        network.execute(chain1, &RpgGameOperation::AddExperience {
             player_id: player_id.clone(),
-            inventory: inventory.clone(),
+            amount: player_data.experience,
         }).await.unwrap();
 
-        builder.call_application(chain1, app1, &rpg_game::RpgGameOperation::SaveQuests {
+        // Both chains know the items, so the inventory can move with the player
+        for chain_id in [chain1, chain2] {
+            for (item_id, slot_type) in [("magic_sword", rpg_game::EquipmentSlot::Weapon), ("magic_armor", rpg_game::EquipmentSlot::Chest)] {
+                network.execute(chain_id, &RpgGameOperation::DefineItem {
+                    definition: item_definition(item_id, Some(slot_type)),
+                }).await.unwrap();
+            }
+        }
+
+        for item_id in ["magic_sword", "magic_armor"] {
+            network.execute(chain1, &grant(&player_id, item_id)).await.unwrap();
+        }
+        network.execute(chain1, &RpgGameOperation::SaveInventory {
+            player_id: player_id.clone(),
+            items: inventory.clone(),
+        }).await.unwrap();
+
+        network.execute(chain1, &RpgGameOperation::SaveQuests {
             player_id: player_id.clone(),
             quests: quests.clone(),
         }).await.unwrap();
 
-        // Initiate the transfer to chain2
-        let auth_token = "auth_token_123".to_string();
-        
-        builder.call_application(chain1, app1, &rpg_game::RpgGameOperation::TransferPlayer {
+        // Initiate the transfer to chain2; the source chain issues the transfer token
+        // and sends the player as stored on chain1
+        let response = network.execute(chain1, &RpgGameOperation::TransferPlayer {
             player_id: player_id.clone(),
             destination_chain: chain2,
-            player_state: player_data.clone(),
-            inventory: inventory.clone(),
-            quests: quests.clone(),
-            auth_token: auth_token.clone(),
         }).await.unwrap();
+        assert!(matches!(response, RpgGameResponse::TransferStarted { .. }));
+
+        let transfer_request = network.view(chain1).await
+            .player_transfer_requests.get(&player_id).await.unwrap().unwrap();
+        assert_eq!(transfer_request.destination_chain, chain2);
+        assert!(!transfer_request.auth_token.is_empty());
+        assert_eq!(transfer_request.status, rpg_game::TransferStatus::Pending);
+
+        // The player is locked on chain1 while the transfer is in flight
+        let response = network.execute(chain1, &RpgGameOperation::SavePlayerState {
+            player_id: player_id.clone(),
+            health: 100,
+            max_health: 100,
+            strength: player_data.strength,
+            wisdomness: player_data.wisdomness,
+            benchpress: player_data.benchpress,
+            curl: player_data.curl,
+            experience: player_data.experience,
+            level: player_data.level,
+        }).await;
+        assert!(refusal(response).contains(&RpgGameError::TransferLocked(player_id.clone()).to_string()));
+        let state_chain1 = network.view(chain1).await;
+        assert_eq!(state_chain1.player_states.get(&player_id).await.unwrap().unwrap().health, 80);
 
         // Process the cross-chain message on chain2
-        builder.process_inbox(chain2).await.unwrap();
+        network.process_inbox(chain2).await;
 
         // Verify player was transferred to chain2
-        let state_chain2 = builder.view(chain2, app2).await.unwrap();
+        let state_chain2 = network.view(chain2).await;
         let transferred_player = state_chain2.player_states.get(&player_id).await.unwrap().unwrap();
         assert_eq!(transferred_player.health, 80);
-        assert_eq!(transferred_player.strength, 15);
+        assert_eq!(transferred_player.strength, 11);
 
         let transferred_inventory = state_chain2.player_inventories.get(&player_id).await.unwrap().unwrap();
-        assert_eq!(transferred_inventory.items.len(), 2);
+        assert_eq!(transferred_inventory.items, inventory);
 
         let transferred_quests = state_chain2.player_quests.get(&player_id).await.unwrap().unwrap();
-        assert_eq!(transferred_quests.len(), 1);
+        assert_eq!(transferred_quests, quests);
+
+        // The token was accepted once and cannot be replayed
+        assert!(state_chain2.consumed_transfer_tokens.contains_key(&transfer_request.auth_token).await.unwrap());
+
+        // Acknowledge the transfer on chain1, which releases the source copy
+        network.process_inbox(chain1).await;
+
+        let state_chain1 = network.view(chain1).await;
+        let transfer_request = state_chain1.player_transfer_requests.get(&player_id).await.unwrap().unwrap();
+        assert_eq!(transfer_request.status, rpg_game::TransferStatus::Completed);
+        assert!(transfer_request.failure_reason.is_none());
+        assert!(state_chain1.player_states.get(&player_id).await.unwrap().is_none());
+        assert!(state_chain1.player_inventories.get(&player_id).await.unwrap().is_none());
+        assert!(state_chain1.player_quests.get(&player_id).await.unwrap().is_none());
+
+        // The player keeps its owner on the destination chain
+        let owner_chain1 = network.view(chain1).await
+            .player_owners.get(&player_id).await.unwrap().unwrap();
+        let owner_chain2 = state_chain2.player_owners.get(&player_id).await.unwrap().unwrap();
+        assert_eq!(owner_chain1, owner_chain2);
     }
 
     #[tokio::test]
     async fn test_cross_chain_guild_join() {
         // Test cross-chain guild join functionality
-        let (mut builder, _committee) = TestBuilder::new()
-            .with_base_layer(BaseLayer::Simulator)
-            .with_nb_chains(2)
-            .build();
+        let mut network = Network::default();
         
-        let chain1 = ChainId::root(0);
-        let chain2 = ChainId::root(1);
+        let chain1 = chain(0);
+        let chain2 = chain(1);
 
         // Deploy the application to both chains
-        let app1 = builder.publish_and_create::<rpg_game::RpgGameAbi, String, String, _>(
-            chain1,
-            "world1".to_string(),
-            "world1".to_string(),
-            &(),
-            &mut BTreeMap::default(),
-        ).await.unwrap();
-
-        let app2 = builder.publish_and_create::<rpg_game::RpgGameAbi, String, String, _>(
-            chain2,
-            "world2".to_string(),
-            "world2".to_string(),
-            &(),
-            &mut BTreeMap::default(),
-        ).await.unwrap();
+        network.create_application(chain1, parameters("world1")).await;
+
+        network.create_application(chain2, parameters("world2")).await;
 
         let player_id = "guild_member".to_string();
         let guild_id = "adventurers_guild".to_string();
 
+        network.execute(chain1, &RpgGameOperation::RegisterPlayer {
+            player_id: player_id.clone(),
+        }).await.unwrap();
+
         // Create a guild on chain2 (by joining it from chain1)
-        builder.call_application(chain1, app1, &rpg_game::RpgGameOperation::JoinGuild {
+        network.execute(chain1, &RpgGameOperation::JoinGuild {
             player_id: player_id.clone(),
             guild_id: guild_id.clone(),
             chain_id: chain2,
         }).await.unwrap();
 
         // Process the cross-chain message on chain2
-        builder.process_inbox(chain2).await.unwrap();
+        network.process_inbox(chain2).await;
 
         // Verify guild was created on chain2 and player is a member
-        let state_chain2 = builder.view(chain2, app2).await.unwrap();
+        let state_chain2 = network.view(chain2).await;
         let guild = state_chain2.guilds.get(&guild_id).await.unwrap().unwrap();
         assert!(guild.members.contains(&player_id));
 
@@ -250,25 +473,20 @@ mod tests {
     #[tokio::test]
     async fn test_battle_recording() {
         // Test verifiable battle system
-        let (mut builder, _committee) = TestBuilder::new()
-            .with_base_layer(BaseLayer::Simulator)
-            .with_nb_chains(1)
-            .build();
+        let mut network = Network::default();
         
-        let chain1 = ChainId::root(0);
+        let chain1 = chain(0);
 
-        let app = builder.publish_and_create::<rpg_game::RpgGameAbi, String, String, _>(
-            chain1,
-            "world1".to_string(),
-            "world1".to_string(),
-            &(),
-            &mut BTreeMap::default(),
-        ).await.unwrap();
+        network.create_application(chain1, parameters("world1")).await;
 
         let player_id = "battle_tester".to_string();
 
+        network.execute(chain1, &RpgGameOperation::RegisterPlayer {
+            player_id: player_id.clone(),
+        }).await.unwrap();
+
         // Record multiple battles
-        builder.call_application(chain1, app, &rpg_game::RpgGameOperation::RecordBattle {
+        network.execute(chain1, &RpgGameOperation::RecordBattle {
             battle_id: "battle1".to_string(),
             player_id: player_id.clone(),
             opponent: "orc".to_string(),
@@ -278,7 +496,7 @@ mod tests {
             experience_gained: 50,
         }).await.unwrap();
 
-        builder.call_application(chain1, app, &rpg_game::RpgGameOperation::RecordBattle {
+        network.execute(chain1, &RpgGameOperation::RecordBattle {
             battle_id: "battle2".to_string(),
             player_id: player_id.clone(),
             opponent: "troll".to_string(),
@@ -289,7 +507,7 @@ mod tests {
         }).await.unwrap();
 
         // Verify battles were recorded
-        let state = builder.view(chain1, app).await.unwrap();
+        let state = network.view(chain1).await;
         
         let battle1 = state.battle_records.get(&"battle1".to_string()).await.unwrap().unwrap();
         assert_eq!(battle1.opponent, "orc");
@@ -305,4 +523,1146 @@ mod tests {
         assert!(player_battles.contains(&"battle1".to_string()));
         assert!(player_battles.contains(&"battle2".to_string()));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_unregistered_player_writes_are_rejected() {
+        let mut network = Network::default();
+
+        let chain1 = chain(0);
+
+        network.create_application(chain1, parameters("world1")).await;
+
+        let player_id = "unregistered_player".to_string();
+
+        // Saving state for a player nobody registered must not write anything
+        let response = network.execute(chain1, &RpgGameOperation::SavePlayerState {
+            player_id: player_id.clone(),
+            health: 100,
+            max_health: 100,
+            strength: 999,
+            wisdomness: 999,
+            benchpress: 999,
+            curl: 999,
+            experience: 0,
+            level: 99,
+        }).await;
+        assert!(refusal(response).contains(&RpgGameError::UnknownPlayer(player_id.clone()).to_string()));
+
+        let state = network.view(chain1).await;
+        assert!(state.player_states.get(&player_id).await.unwrap().is_none());
+
+        // Registering the same player twice keeps the original owner
+        network.execute(chain1, &RpgGameOperation::RegisterPlayer {
+            player_id: player_id.clone(),
+        }).await.unwrap();
+        let owner = network.view(chain1).await
+            .player_owners.get(&player_id).await.unwrap().unwrap();
+
+        let response = network.execute(chain1, &RpgGameOperation::RegisterPlayer {
+            player_id: player_id.clone(),
+        }).await;
+        assert!(refusal(response).contains(&RpgGameError::PlayerAlreadyRegistered(player_id.clone()).to_string()));
+        let state = network.view(chain1).await;
+        assert_eq!(state.player_owners.get(&player_id).await.unwrap().unwrap(), owner);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_player_transfer_unlocks_player() {
+        let mut network = Network::default();
+
+        let chain1 = chain(0);
+        let chain2 = chain(1);
+
+        network.create_application(chain1, parameters("world1")).await;
+
+        network.create_application(chain2, parameters("world2")).await;
+
+        let player_id = "duplicated_player".to_string();
+        let save_state = RpgGameOperation::SavePlayerState {
+            player_id: player_id.clone(),
+            health: 100,
+            max_health: 100,
+            strength: 10,
+            wisdomness: 5,
+            benchpress: 5,
+            curl: 5,
+            experience: 0,
+            level: 1,
+        };
+
+        // The same player ID already exists on both chains
+        for chain_id in [chain1, chain2] {
+            network.execute(chain_id, &RpgGameOperation::RegisterPlayer {
+                player_id: player_id.clone(),
+            }).await.unwrap();
+            network.execute(chain_id, &save_state).await.unwrap();
+        }
+
+        network.execute(chain1, &RpgGameOperation::TransferPlayer {
+            player_id: player_id.clone(),
+            destination_chain: chain2,
+        }).await.unwrap();
+
+        // chain2 refuses the transfer and chain1 records why
+        network.process_inbox(chain2).await;
+        network.process_inbox(chain1).await;
+
+        let state_chain1 = network.view(chain1).await;
+        let transfer_request = state_chain1.player_transfer_requests.get(&player_id).await.unwrap().unwrap();
+        assert_eq!(transfer_request.status, rpg_game::TransferStatus::Failed);
+        assert!(transfer_request.failure_reason.is_some());
+        assert!(state_chain1.player_states.get(&player_id).await.unwrap().is_some());
+
+        // The player is editable again on chain1
+        network.execute(chain1, &RpgGameOperation::SavePlayerState {
+            player_id: player_id.clone(),
+            health: 90,
+            max_health: 100,
+            strength: 10,
+            wisdomness: 5,
+            benchpress: 5,
+            curl: 5,
+            experience: 0,
+            level: 1,
+        }).await.unwrap();
+        let state_chain1 = network.view(chain1).await;
+        assert_eq!(state_chain1.player_states.get(&player_id).await.unwrap().unwrap().health, 90);
+    }
+
+    #[test]
+    fn test_transfer_token_checks() {
+        let ticket = rpg_game::TransferTicket {
+            player_id: "traveller".to_string(),
+            owner: linera_sdk::linera_base_types::AccountOwner::Address20([1; 20]),
+            source_chain: chain(0),
+            destination_chain: chain(1),
+            nonce: 0,
+            player_state: rpg_game::PlayerState {
+                health: 100,
+                max_health: 100,
+                strength: 10,
+                wisdomness: 5,
+                benchpress: 5,
+                curl: 5,
+                experience: 0,
+                level: 1,
+            },
+            inventory: "[]".to_string(),
+            quests: "[]".to_string(),
+            balance: 40,
+        };
+        let token = ticket.token();
+        assert_eq!(ticket.check(&token, false), Ok(()));
+
+        // A token issued for other stats, another balance or another route does not cover this transfer;
+        // the destination reports the error back to the source chain, which records it as the failure reason
+        let forgeries = [
+            rpg_game::TransferTicket {
+                player_state: rpg_game::PlayerState { strength: 999, ..ticket.player_state.clone() },
+                ..ticket.clone()
+            },
+            rpg_game::TransferTicket { balance: 1_000_000, ..ticket.clone() },
+            rpg_game::TransferTicket { destination_chain: chain(2), ..ticket.clone() },
+        ];
+        for forgery in forgeries {
+            assert_eq!(ticket.check(&forgery.token(), false), Err(RpgGameError::InvalidTransferToken));
+        }
+
+        // A token the destination already accepted is refused the second time
+        assert_eq!(ticket.check(&token, true), Err(RpgGameError::TransferTokenReused));
+    }
+
+    #[tokio::test]
+    async fn test_server_authoritative_progression() {
+        let mut network = Network::default();
+
+        let chain1 = chain(0);
+
+        network.create_application(chain1, parameters("world1")).await;
+
+        let player_id = "levelling_player".to_string();
+
+        network.execute(chain1, &RpgGameOperation::RegisterPlayer {
+            player_id: player_id.clone(),
+        }).await.unwrap();
+
+        let save = |strength, wisdomness, benchpress, curl, experience, level| RpgGameOperation::SavePlayerState {
+            player_id: player_id.clone(),
+            health: 100,
+            max_health: 100,
+            strength,
+            wisdomness,
+            benchpress,
+            curl,
+            experience,
+            level,
+        };
+
+        // A new character starts at level 1, without experience and with the starting stats
+        let response = network.execute(chain1, &save(10, 5, 5, 5, 1000, 1)).await;
+        assert!(refusal(response).contains("inconsistent with the recorded progression"));
+        let response = network.execute(chain1, &save(50, 5, 20, 100, 0, 1)).await;
+        assert!(refusal(response).contains(&RpgGameError::InconsistentProgression {
+            field: "strength".to_string(),
+            expected: 10,
+            actual: 50,
+        }.to_string()));
+
+        network.execute(chain1, &save(10, 5, 5, 5, 0, 1)).await.unwrap();
+
+        // 100 experience reaches level 2, 300 in total reaches level 3
+        let response = network.execute(chain1, &RpgGameOperation::AddExperience {
+            player_id: player_id.clone(),
+            amount: 300,
+        }).await.unwrap();
+        assert_eq!(response, RpgGameResponse::ExperienceAdded { level: 3, levels_gained: 2 });
+
+        let state = network.view(chain1).await;
+        let player = state.player_states.get(&player_id).await.unwrap().unwrap();
+        assert_eq!(player.experience, 300);
+        assert_eq!(player.strength, 12);
+        assert_eq!(player.wisdomness, 7);
+        assert_eq!(player.benchpress, 7);
+        assert_eq!(player.curl, 9);
+
+        // The client cannot save stats the chain did not grant
+        let response = network.execute(chain1, &save(20, 7, 7, 9, 300, 3)).await;
+        assert!(refusal(response).contains(&RpgGameError::InconsistentProgression {
+            field: "strength".to_string(),
+            expected: 12,
+            actual: 20,
+        }.to_string()));
+
+        // Experience beyond the last level cannot overflow the level or the stats
+        let response = network.execute(chain1, &RpgGameOperation::AddExperience {
+            player_id: player_id.clone(),
+            amount: u64::MAX,
+        }).await.unwrap();
+        assert_eq!(response, RpgGameResponse::ExperienceAdded {
+            level: rpg_game::progression::MAX_LEVEL,
+            levels_gained: rpg_game::progression::MAX_LEVEL - 3,
+        });
+    }
+
+    #[test]
+    fn test_level_is_capped() {
+        use rpg_game::progression::{experience_required, level_for_experience, MAX_LEVEL};
+
+        assert_eq!(level_for_experience(1, 99), 1);
+        assert_eq!(level_for_experience(1, 100), 2);
+        assert_eq!(experience_required(MAX_LEVEL), u64::MAX);
+        assert_eq!(level_for_experience(1, u64::MAX), MAX_LEVEL);
+    }
+
+    #[tokio::test]
+    async fn test_save_rules_without_authoritative_progression() {
+        let mut network = Network::default();
+
+        let chain1 = chain(0);
+
+        // Let the client report progression, within the delta rules
+        let parameters = rpg_game::Parameters {
+            world_region: "world1".to_string(),
+            hub_application_id: None,
+            hub_chain_id: None,
+            save_rules: rpg_game::SaveRules {
+                authoritative_progression: false,
+                ..rpg_game::SaveRules::default()
+            },
+            admins: Vec::new(),
+        };
+        network.create_application(chain1, parameters).await;
+
+        let player_id = "reporting_player".to_string();
+        let save = |health, strength, experience, level| RpgGameOperation::SavePlayerState {
+            player_id: player_id.clone(),
+            health,
+            max_health: 100,
+            strength,
+            wisdomness: 5,
+            benchpress: 5,
+            curl: 5,
+            experience,
+            level,
+        };
+
+        network.execute(chain1, &RpgGameOperation::RegisterPlayer {
+            player_id: player_id.clone(),
+        }).await.unwrap();
+
+        // The first save is checked against a new level 1 character
+        let response = network.execute(chain1, &save(100, 10, 5000, 50)).await;
+        assert!(refusal(response).contains(&format!("violates the {} rule", rpg_game::SaveInvariant::MaxLevelsPerSave)));
+        let response = network.execute(chain1, &save(100, 999, 0, 1)).await;
+        assert!(refusal(response).contains(&format!("violates the {} rule", rpg_game::SaveInvariant::MaxStatGainPerSave)));
+        network.execute(chain1, &save(100, 10, 0, 1)).await.unwrap();
+
+        // One level's worth of progress is accepted
+        network.execute(chain1, &save(100, 11, 120, 2)).await.unwrap();
+
+        let cheats = [
+            (save(150, 11, 120, 2), rpg_game::SaveInvariant::HealthWithinMax),
+            (save(100, 11, 50, 2), rpg_game::SaveInvariant::ExperienceNotDecreasing),
+            (save(100, 11, 120, 1), rpg_game::SaveInvariant::LevelNotDecreasing),
+            (save(100, 11, 5000, 6), rpg_game::SaveInvariant::MaxLevelsPerSave),
+            (save(100, 50, 300, 3), rpg_game::SaveInvariant::MaxStatGainPerSave),
+        ];
+        for (operation, expected) in cheats {
+            let response = network.execute(chain1, &operation).await;
+            assert!(refusal(response).contains(&format!("violates the {} rule", expected)));
+        }
+
+        let state = network.view(chain1).await;
+        let player = state.player_states.get(&player_id).await.unwrap().unwrap();
+        assert_eq!(player.level, 2);
+        assert_eq!(player.strength, 11);
+    }
+
+    #[tokio::test]
+    async fn test_milestones_are_reported_to_hub() {
+        let mut network = Network::default();
+
+        let hub_chain = chain(0);
+        let world_chain = chain(1);
+
+        let parameters = rpg_game::Parameters {
+            hub_application_id: Some(ApplicationId::new(CryptoHash::test_hash("hub")).with_abi()),
+            hub_chain_id: Some(hub_chain),
+            ..parameters("world1")
+        };
+        network.create_application(world_chain, parameters).await;
+
+        let player_id = "streaker".to_string();
+        network.execute(world_chain, &RpgGameOperation::RegisterPlayer {
+            player_id: player_id.clone(),
+        }).await.unwrap();
+
+        // Three wins in a row earn a streak achievement
+        for battle in 1..=3 {
+            network.execute(world_chain, &RpgGameOperation::RecordBattle {
+                battle_id: format!("battle{}", battle),
+                player_id: player_id.clone(),
+                opponent: "goblin".to_string(),
+                player_result: 2,
+                damage_dealt: 30,
+                damage_taken: 5,
+                experience_gained: 20,
+            }).await.unwrap();
+        }
+
+        // Completing a quest of the catalogue earns an achievement; saving other quests as completed does not
+        network.execute(world_chain, &RpgGameOperation::DefineQuest {
+            definition: rpg_game::QuestDefinition {
+                id: "quest1".to_string(),
+                title: "First Quest".to_string(),
+                reward: 0,
+            },
+        }).await.unwrap();
+        network.execute(world_chain, &RpgGameOperation::CompleteQuest {
+            player_id: player_id.clone(),
+            quest_id: "quest1".to_string(),
+        }).await.unwrap();
+        network.execute(world_chain, &RpgGameOperation::SaveQuests {
+            player_id: player_id.clone(),
+            quests: vec![quest("quest1", "First Quest", true, 1), quest("made_up", "Free Achievement", true, 1)],
+        }).await.unwrap();
+
+        let hub_calls = network.hub_calls.borrow();
+        let achievement_ids = hub_calls.iter().filter_map(|operation| match operation {
+            HubOperation::ReportAchievement { hub_chain_id, player_id: reported, achievement_id, .. } => {
+                assert_eq!((*hub_chain_id, reported), (hub_chain, &player_id));
+                Some(achievement_id.as_str())
+            }
+            _ => None,
+        }).collect::<Vec<_>>();
+        assert_eq!(achievement_ids, ["win_streak_3", "quest_quest1"]);
+        let battle_wins = hub_calls.iter().map(|operation| match operation {
+            HubOperation::ReportPlayerProgress { battle_wins_gained, .. } => *battle_wins_gained,
+            _ => 0,
+        }).sum::<u64>();
+        assert_eq!(battle_wins, 3);
+    }
+
+    #[tokio::test]
+    async fn test_admins_on_other_chains() {
+        let mut network = Network::default();
+
+        let chain1 = chain(0);
+        let chain2 = chain(1);
+        let admin = AccountOwner::Address20([2; 20]);
+
+        let parameters = rpg_game::Parameters {
+            admins: vec![admin],
+            ..parameters("world1")
+        };
+        network.create_application(chain1, parameters).await;
+        network.add_chain(chain2, chain1).await;
+
+        let define = |item_id: &str| RpgGameOperation::DefineItem {
+            definition: item_definition(item_id, Some(rpg_game::EquipmentSlot::Weapon)),
+        };
+
+        // The creator manages the creator chain only
+        network.execute(chain1, &define("sword1")).await.unwrap();
+        let response = network.execute(chain2, &define("sword1")).await;
+        assert_eq!(response, Err(RpgGameError::NotAdmin));
+
+        // Listed admins manage every chain, so transferred players find their items there too
+        network.signer = admin;
+        for chain_id in [chain1, chain2] {
+            network.execute(chain_id, &define("axe1")).await.unwrap();
+        }
+        network.signer = OWNER;
+        network.execute(chain2, &RpgGameOperation::RegisterPlayer {
+            player_id: "traveller".to_string(),
+        }).await.unwrap();
+        let response = network.execute(chain2, &grant("traveller", "axe1")).await;
+        assert_eq!(response, Err(RpgGameError::NotAdmin));
+        network.signer = admin;
+        network.execute(chain2, &grant("traveller", "axe1")).await.unwrap();
+
+        let state = network.view(chain2).await;
+        assert!(state.item_catalogue.get(&"sword1".to_string()).await.unwrap().is_none());
+        let inventory = state.player_inventories.get(&"traveller".to_string()).await.unwrap().unwrap();
+        assert_eq!(inventory.item_in_slot("inventory-1").unwrap().item_id, "axe1");
+    }
+
+    #[tokio::test]
+    async fn test_inventory_operations() {
+        let mut network = Network::default();
+
+        let chain1 = chain(0);
+
+        network.create_application(chain1, parameters("world1")).await;
+
+        let player_id = "collector".to_string();
+        network.execute(chain1, &RpgGameOperation::RegisterPlayer {
+            player_id: player_id.clone(),
+        }).await.unwrap();
+
+        for (item_id, slot_type) in [
+            ("sword1", rpg_game::EquipmentSlot::Weapon),
+            ("axe1", rpg_game::EquipmentSlot::Weapon),
+            ("shield1", rpg_game::EquipmentSlot::Offhand),
+        ] {
+            network.execute(chain1, &RpgGameOperation::DefineItem {
+                definition: rpg_game::ItemDefinition {
+                    params: rpg_game::ItemParams(json!({"damage": 3})),
+                    ..item_definition(item_id, Some(slot_type))
+                },
+            }).await.unwrap();
+        }
+
+        // New items fill the first free bag slot
+        for item_id in ["sword1", "axe1"] {
+            network.execute(chain1, &grant(&player_id, item_id)).await.unwrap();
+        }
+
+        let response = network.execute(chain1, &RpgGameOperation::AddItem {
+            player_id: player_id.clone(),
+            item_id: "shield1".to_string(),
+            quantity: 1,
+            slot: Some("inventory-2".to_string()),
+        }).await;
+        assert!(refusal(response).contains(&RpgGameError::SlotOccupied("inventory-2".to_string()).to_string()));
+
+        let response = network.execute(chain1, &RpgGameOperation::EquipItem {
+            player_id: player_id.clone(),
+            from_slot: "inventory-1".to_string(),
+            equip_slot: "inventory-equip-1".to_string(),
+        }).await.unwrap();
+        assert_eq!(response, RpgGameResponse::ItemPlaced { slot: "inventory-equip-1".to_string() });
+
+        // Slots only go by their canonical names, so an alias cannot hold a second item in the same slot
+        let response = network.execute(chain1, &RpgGameOperation::EquipItem {
+            player_id: player_id.clone(),
+            from_slot: "inventory-2".to_string(),
+            equip_slot: "inventory-equip-01".to_string(),
+        }).await;
+        assert!(refusal(response).contains(&RpgGameError::InvalidSlot("inventory-equip-01".to_string()).to_string()));
+        let response = network.execute(chain1, &RpgGameOperation::MoveItem {
+            player_id: player_id.clone(),
+            from_slot: "inventory-2".to_string(),
+            to_slot: "inventory-01".to_string(),
+        }).await;
+        assert!(refusal(response).contains(&RpgGameError::InvalidSlot("inventory-01".to_string()).to_string()));
+
+        // Equipment slots only take one item, and only from the bag
+        let response = network.execute(chain1, &RpgGameOperation::EquipItem {
+            player_id: player_id.clone(),
+            from_slot: "inventory-2".to_string(),
+            equip_slot: "inventory-equip-1".to_string(),
+        }).await;
+        assert!(refusal(response).contains(&RpgGameError::SlotOccupied("inventory-equip-1".to_string()).to_string()));
+
+        let response = network.execute(chain1, &RpgGameOperation::MoveItem {
+            player_id: player_id.clone(),
+            from_slot: "inventory-equip-1".to_string(),
+            to_slot: "inventory-5".to_string(),
+        }).await;
+        assert!(refusal(response).contains(&RpgGameError::InvalidSlot("inventory-equip-1".to_string()).to_string()));
+
+        network.execute(chain1, &RpgGameOperation::MoveItem {
+            player_id: player_id.clone(),
+            from_slot: "inventory-2".to_string(),
+            to_slot: "inventory-5".to_string(),
+        }).await.unwrap();
+
+        let response = network.execute(chain1, &RpgGameOperation::RemoveItem {
+            player_id: player_id.clone(),
+            slot: "inventory-2".to_string(),
+        }).await;
+        assert!(refusal(response).contains(&RpgGameError::EmptySlot("inventory-2".to_string()).to_string()));
+
+        let response = network.execute(chain1, &RpgGameOperation::UnequipItem {
+            player_id: player_id.clone(),
+            equip_slot: "inventory-equip-1".to_string(),
+            to_slot: None,
+        }).await.unwrap();
+        assert_eq!(response, RpgGameResponse::ItemPlaced { slot: "inventory-1".to_string() });
+
+        // Whole-inventory saves must keep one item per valid slot
+        let response = network.execute(chain1, &RpgGameOperation::SaveInventory {
+            player_id: player_id.clone(),
+            items: vec![item("inventory-1", "sword1"), item("inventory-1", "axe1")],
+        }).await;
+        assert!(refusal(response).contains(&RpgGameError::SlotOccupied("inventory-1".to_string()).to_string()));
+
+        // Whole-inventory saves only rearrange the items held
+        let response = network.execute(chain1, &RpgGameOperation::SaveInventory {
+            player_id: player_id.clone(),
+            items: vec![item("inventory-1", "sword1"), item("inventory-5", "axe1"), item("inventory-6", "axe1")],
+        }).await;
+        assert!(refusal(response).contains(&RpgGameError::InventoryMismatch(
+            "1 units of item axe1 are not held".to_string(),
+        ).to_string()));
+        let response = network.execute(chain1, &RpgGameOperation::SaveInventory {
+            player_id: player_id.clone(),
+            items: vec![item("inventory-1", "sword1")],
+        }).await;
+        assert!(refusal(response).contains(&RpgGameError::InventoryMismatch(
+            "the stack in slot inventory-5 is missing".to_string(),
+        ).to_string()));
+
+        network.execute(chain1, &RpgGameOperation::SaveInventory {
+            player_id: player_id.clone(),
+            items: vec![item("inventory-equip-1", "axe1"), item("inventory-3", "sword1")],
+        }).await.unwrap();
+
+        // Items keep the parameters of the catalogue, whatever the client sends
+        let state = network.view(chain1).await;
+        let inventory = state.player_inventories.get(&player_id).await.unwrap().unwrap();
+        assert_eq!(inventory.item_in_slot("inventory-3").unwrap().item_id, "sword1");
+        assert_eq!(inventory.item_in_slot("inventory-equip-1").unwrap().item_id, "axe1");
+        assert_eq!(inventory.items.len(), 2);
+        assert!(inventory.items.iter().all(|item| item.params == rpg_game::ItemParams(json!({"damage": 3}))));
+    }
+
+    #[test]
+    fn test_slot_names() {
+        use rpg_game::{EquipmentSlot, SlotKind};
+
+        assert_eq!(SlotKind::of("inventory-1"), Some(SlotKind::Bag));
+        assert_eq!(SlotKind::of("inventory-24"), Some(SlotKind::Bag));
+        assert_eq!(SlotKind::of("inventory-equip-8"), Some(SlotKind::Equip));
+        assert_eq!(EquipmentSlot::of("inventory-equip-1"), Some(EquipmentSlot::Weapon));
+
+        for slot in ["inventory-0", "inventory-25", "inventory-equip-9", "inventory-01", "inventory-+1", "inventory-0001", "inventory-equip-01", "inventory-equip-+1"] {
+            assert_eq!(SlotKind::of(slot), None, "{} is not a slot", slot);
+            assert_eq!(EquipmentSlot::of(slot), None, "{} is not an equipment slot", slot);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_item_catalogue() {
+        let mut network = Network::default();
+
+        let chain1 = chain(0);
+
+        network.create_application(chain1, parameters("world1")).await;
+
+        let player_id = "crafter".to_string();
+        network.execute(chain1, &RpgGameOperation::RegisterPlayer {
+            player_id: player_id.clone(),
+        }).await.unwrap();
+
+        network.execute(chain1, &RpgGameOperation::DefineItem {
+            definition: item_definition("sword1", Some(rpg_game::EquipmentSlot::Weapon)),
+        }).await.unwrap();
+        network.execute(chain1, &RpgGameOperation::DefineItem {
+            definition: rpg_game::ItemDefinition {
+                stack_size: 5,
+                ..item_definition("potion", None)
+            },
+        }).await.unwrap();
+
+        let response = network.execute(chain1, &RpgGameOperation::DefineItem {
+            definition: rpg_game::ItemDefinition {
+                stack_size: 0,
+                ..item_definition("dust", None)
+            },
+        }).await;
+        assert!(refusal(response).contains("invalid item definition"));
+
+        // Only catalogue items can be granted
+        let response = network.execute(chain1, &grant(&player_id, "sword_of_999")).await;
+        assert!(refusal(response).contains(&RpgGameError::UnknownItem("sword_of_999".to_string()).to_string()));
+
+        // Items only go to equipment slots of their type
+        network.execute(chain1, &grant(&player_id, "sword1")).await.unwrap();
+        let response = network.execute(chain1, &RpgGameOperation::SaveInventory {
+            player_id: player_id.clone(),
+            items: vec![item("inventory-equip-3", "sword1")],
+        }).await;
+        assert!(refusal(response).contains(&RpgGameError::IncompatibleSlot {
+            item_id: "sword1".to_string(),
+            slot: "inventory-equip-3".to_string(),
+        }.to_string()));
+
+        // Stacks are limited by the stack size
+        let add_potions = |quantity| RpgGameOperation::AddItem {
+            player_id: player_id.clone(),
+            item_id: "potion".to_string(),
+            quantity,
+            slot: None,
+        };
+        let response = network.execute(chain1, &add_potions(6)).await;
+        assert!(refusal(response).contains(&RpgGameError::InvalidQuantity {
+            item_id: "potion".to_string(),
+            quantity: 6,
+            stack_size: 5,
+        }.to_string()));
+        network.execute(chain1, &add_potions(5)).await.unwrap();
+
+        let response = network.execute(chain1, &RpgGameOperation::EquipItem {
+            player_id: player_id.clone(),
+            from_slot: "inventory-2".to_string(),
+            equip_slot: "inventory-equip-1".to_string(),
+        }).await;
+        assert!(refusal(response).contains(&RpgGameError::IncompatibleSlot {
+            item_id: "potion".to_string(),
+            slot: "inventory-equip-1".to_string(),
+        }.to_string()));
+
+        let state = network.view(chain1).await;
+        let inventory = state.player_inventories.get(&player_id).await.unwrap().unwrap();
+        assert_eq!(inventory.items.len(), 2);
+        assert_eq!(inventory.item_in_slot("inventory-2").unwrap().quantity, 5);
+    }
+
+    #[tokio::test]
+    async fn test_effective_stats() {
+        let mut network = Network::default();
+
+        let chain1 = chain(0);
+
+        network.create_application(chain1, parameters("world1")).await;
+
+        let player_id = "paladin".to_string();
+        network.execute(chain1, &RpgGameOperation::RegisterPlayer {
+            player_id: player_id.clone(),
+        }).await.unwrap();
+
+        let save = |health| RpgGameOperation::SavePlayerState {
+            player_id: player_id.clone(),
+            health,
+            max_health: 100,
+            strength: 10,
+            wisdomness: 5,
+            benchpress: 5,
+            curl: 5,
+            experience: 0,
+            level: 1,
+        };
+        network.execute(chain1, &save(100)).await.unwrap();
+
+        for (item_id, slot_type, stat_modifiers) in [
+            ("greatsword", rpg_game::EquipmentSlot::Weapon, rpg_game::StatModifiers { strength: 5, wisdomness: -2, ..Default::default() }),
+            ("amulet", rpg_game::EquipmentSlot::Accessory, rpg_game::StatModifiers { max_health: 20, ..Default::default() }),
+        ] {
+            network.execute(chain1, &RpgGameOperation::DefineItem {
+                definition: rpg_game::ItemDefinition {
+                    stat_modifiers,
+                    ..item_definition(item_id, Some(slot_type))
+                },
+            }).await.unwrap();
+            network.execute(chain1, &grant(&player_id, item_id)).await.unwrap();
+        }
+
+        // Only equipped items count
+        network.execute(chain1, &RpgGameOperation::SaveInventory {
+            player_id: player_id.clone(),
+            items: vec![item("inventory-equip-1", "greatsword"), item("inventory-1", "amulet")],
+        }).await.unwrap();
+
+        let response = network.execute(chain1, &save(115)).await;
+        assert!(refusal(response).contains(&format!("violates the {} rule", rpg_game::SaveInvariant::HealthWithinMax)));
+
+        network.execute(chain1, &RpgGameOperation::EquipItem {
+            player_id: player_id.clone(),
+            from_slot: "inventory-1".to_string(),
+            equip_slot: "inventory-equip-8".to_string(),
+        }).await.unwrap();
+        network.execute(chain1, &save(115)).await.unwrap();
+
+        let state = network.view(chain1).await;
+        let stats = state.effective_stats(&player_id).await.unwrap();
+        assert_eq!(stats.max_health, 120);
+        assert_eq!(stats.health, 115);
+        assert_eq!(stats.strength, 15);
+        assert_eq!(stats.wisdomness, 3);
+
+        // Battles are checked and recorded against the effective stats
+        let battle = |battle_id: &str, damage_taken| RpgGameOperation::RecordBattle {
+            battle_id: battle_id.to_string(),
+            player_id: player_id.clone(),
+            opponent: "troll".to_string(),
+            player_result: 2,
+            damage_dealt: 40,
+            damage_taken,
+            experience_gained: 10,
+        };
+        let response = network.execute(chain1, &battle("battle1", 120)).await;
+        assert!(refusal(response).contains(&RpgGameError::ImplausibleBattle { damage_taken: 120, max_health: 120 }.to_string()));
+
+        network.execute(chain1, &battle("battle2", 110)).await.unwrap();
+
+        let state = network.view(chain1).await;
+        let record = state.battle_records.get(&"battle2".to_string()).await.unwrap().unwrap();
+        assert_eq!(record.player_stats, Some(stats));
+    }
+
+    #[tokio::test]
+    async fn test_item_trading() {
+        let mut network = Network::default();
+
+        let chain1 = chain(0);
+        let chain2 = chain(1);
+
+        network.create_application(chain1, parameters("world1")).await;
+
+        network.create_application(chain2, parameters("world2")).await;
+
+        for chain_id in [chain1, chain2] {
+            for (item_id, stack_size, tradable) in [("gold_coin", 10, true), ("potion", 5, true), ("soulbound_ring", 1, false)] {
+                network.execute(chain_id, &RpgGameOperation::DefineItem {
+                    definition: rpg_game::ItemDefinition {
+                        stack_size,
+                        tradable,
+                        ..item_definition(item_id, None)
+                    },
+                }).await.unwrap();
+            }
+        }
+
+        let players = [(chain1, "alice", "gold_coin", 10), (chain1, "bob", "potion", 5), (chain2, "carol", "potion", 5)];
+        for (chain_id, player_id, item_id, quantity) in players {
+            network.execute(chain_id, &RpgGameOperation::RegisterPlayer {
+                player_id: player_id.to_string(),
+            }).await.unwrap();
+            network.execute(chain_id, &RpgGameOperation::AddItem {
+                player_id: player_id.to_string(),
+                item_id: item_id.to_string(),
+                quantity,
+                slot: None,
+            }).await.unwrap();
+        }
+        network.execute(chain1, &grant("alice", "soulbound_ring")).await.unwrap();
+
+        let trade_item = |item_id: &str, quantity| rpg_game::TradeItem {
+            item_id: item_id.to_string(),
+            quantity,
+        };
+        let propose = |counterparty_id: &str, counterparty_chain, offered, requested| RpgGameOperation::ProposeTrade {
+            player_id: "alice".to_string(),
+            counterparty_id: counterparty_id.to_string(),
+            counterparty_chain,
+            offered,
+            requested,
+        };
+        let trade_id = |response: rpg_game::RpgGameResponse| match response {
+            RpgGameResponse::TradeProposed { trade_id } => trade_id,
+            other => panic!("unexpected response {:?}", other),
+        };
+
+        // Soulbound items and items the player does not have cannot be offered
+        let response = network.execute(chain1, &propose("bob", chain1, vec![trade_item("soulbound_ring", 1)], vec![])).await;
+        assert!(refusal(response).contains(&RpgGameError::ItemNotTradable("soulbound_ring".to_string()).to_string()));
+        let response = network.execute(chain1, &propose("bob", chain1, vec![trade_item("gold_coin", 11)], vec![])).await;
+        assert!(refusal(response).contains(&RpgGameError::NotEnoughItems {
+            item_id: "gold_coin".to_string(),
+            quantity: 11,
+            available: 10,
+        }.to_string()));
+
+        // The offered coins are held in escrow until bob answers
+        let response = network.execute(chain1, &propose("bob", chain1, vec![trade_item("gold_coin", 4)], vec![trade_item("potion", 2)])).await.unwrap();
+        let local_trade = trade_id(response);
+
+        let state = network.view(chain1).await;
+        let alice = state.player_inventories.get(&"alice".to_string()).await.unwrap().unwrap();
+        assert_eq!(alice.item_in_slot("inventory-1").unwrap().quantity, 6);
+        let trade = state.trades.get(&local_trade).await.unwrap().unwrap();
+        assert_eq!(trade.status, rpg_game::TradeStatus::Pending);
+        assert_eq!(trade.escrow.iter().map(|item| item.quantity).sum::<u64>(), 4);
+
+        let response = network.execute(chain1, &RpgGameOperation::TransferPlayer {
+            player_id: "alice".to_string(),
+            destination_chain: chain2,
+        }).await;
+        assert!(refusal(response).contains(&RpgGameError::TradeInProgress {
+            player_id: "alice".to_string(),
+            trade_id: local_trade.clone(),
+        }.to_string()));
+
+        network.execute(chain1, &RpgGameOperation::AcceptTrade {
+            trade_id: local_trade.clone(),
+        }).await.unwrap();
+
+        let state = network.view(chain1).await;
+        let alice = state.player_inventories.get(&"alice".to_string()).await.unwrap().unwrap();
+        let bob = state.player_inventories.get(&"bob".to_string()).await.unwrap().unwrap();
+        assert_eq!(alice.item_in_slot("inventory-3").unwrap().item_id, "potion");
+        assert_eq!(alice.item_in_slot("inventory-3").unwrap().quantity, 2);
+        assert_eq!(bob.item_in_slot("inventory-1").unwrap().quantity, 3);
+        assert_eq!(bob.item_in_slot("inventory-2").unwrap().item_id, "gold_coin");
+        assert_eq!(bob.item_in_slot("inventory-2").unwrap().quantity, 4);
+        assert_eq!(state.trades.get(&local_trade).await.unwrap().unwrap().status, rpg_game::TradeStatus::Completed);
+
+        let response = network.execute(chain1, &RpgGameOperation::RejectTrade {
+            trade_id: local_trade.clone(),
+        }).await;
+        assert!(refusal(response).contains(&RpgGameError::TradeNotPending(local_trade).to_string()));
+
+        // A rejected trade returns the escrow to the proposer
+        let response = network.execute(chain1, &propose("bob", chain1, vec![trade_item("gold_coin", 1)], vec![trade_item("potion", 1)])).await.unwrap();
+        let rejected_trade = trade_id(response);
+        network.execute(chain1, &RpgGameOperation::RejectTrade {
+            trade_id: rejected_trade.clone(),
+        }).await.unwrap();
+
+        let state = network.view(chain1).await;
+        let alice = state.player_inventories.get(&"alice".to_string()).await.unwrap().unwrap();
+        let coins = alice.items.iter().filter(|item| item.item_id == "gold_coin").map(|item| item.quantity).sum::<u64>();
+        assert_eq!(coins, 6);
+        assert_eq!(state.trades.get(&rejected_trade).await.unwrap().unwrap().status, rpg_game::TradeStatus::Rejected);
+
+        // Cross-chain: carol answers on chain2 and chain1 settles the escrow
+        let response = network.execute(chain1, &propose("carol", chain2, vec![trade_item("gold_coin", 2)], vec![trade_item("potion", 1)])).await.unwrap();
+        let remote_trade = trade_id(response);
+        network.process_inbox(chain2).await;
+
+        let trade = network.view(chain2).await
+            .trades.get(&remote_trade).await.unwrap().unwrap();
+        assert_eq!(trade.proposer_chain, chain1);
+        assert_eq!(trade.status, rpg_game::TradeStatus::Pending);
+
+        network.execute(chain2, &RpgGameOperation::AcceptTrade {
+            trade_id: remote_trade.clone(),
+        }).await.unwrap();
+        network.process_inbox(chain1).await;
+        network.process_inbox(chain2).await;
+
+        let state = network.view(chain1).await;
+        let alice = state.player_inventories.get(&"alice".to_string()).await.unwrap().unwrap();
+        let potions = alice.items.iter().filter(|item| item.item_id == "potion").map(|item| item.quantity).sum::<u64>();
+        assert_eq!(potions, 3);
+        let trade = state.trades.get(&remote_trade).await.unwrap().unwrap();
+        assert_eq!(trade.status, rpg_game::TradeStatus::Completed);
+        assert!(trade.escrow.is_empty());
+
+        let state = network.view(chain2).await;
+        let carol = state.player_inventories.get(&"carol".to_string()).await.unwrap().unwrap();
+        assert_eq!(carol.item_in_slot("inventory-1").unwrap().quantity, 4);
+        assert_eq!(carol.item_in_slot("inventory-2").unwrap().item_id, "gold_coin");
+        assert_eq!(carol.item_in_slot("inventory-2").unwrap().quantity, 2);
+        assert_eq!(state.trades.get(&remote_trade).await.unwrap().unwrap().status, rpg_game::TradeStatus::Completed);
+
+        // A proposal for an unknown player fails on chain2 and the escrow comes back
+        let response = network.execute(chain1, &propose("dave", chain2, vec![trade_item("gold_coin", 1)], vec![])).await.unwrap();
+        let failed_trade = trade_id(response);
+        network.process_inbox(chain2).await;
+        network.process_inbox(chain1).await;
+
+        let state = network.view(chain1).await;
+        let trade = state.trades.get(&failed_trade).await.unwrap().unwrap();
+        assert_eq!(trade.status, rpg_game::TradeStatus::Failed);
+        assert!(trade.escrow.is_empty());
+        let alice = state.player_inventories.get(&"alice".to_string()).await.unwrap().unwrap();
+        let coins = alice.items.iter().filter(|item| item.item_id == "gold_coin").map(|item| item.quantity).sum::<u64>();
+        assert_eq!(coins, 4);
+    }
+
+    #[tokio::test]
+    async fn test_currency_ledger() {
+        let mut network = Network::default();
+
+        let chain1 = chain(0);
+        let chain2 = chain(1);
+
+        network.create_application(chain1, parameters("world1")).await;
+        network.create_application(chain2, parameters("world2")).await;
+
+        for (chain_id, player_id) in [(chain1, "alice"), (chain1, "bob"), (chain2, "carol")] {
+            network.execute(chain_id, &RpgGameOperation::RegisterPlayer {
+                player_id: player_id.to_string(),
+            }).await.unwrap();
+        }
+
+        let credit = |amount| RpgGameOperation::CreditCurrency {
+            player_id: "alice".to_string(),
+            amount,
+            reason: "starting gold".to_string(),
+        };
+        let transfer = |recipient_id: &str, recipient_chain, amount| RpgGameOperation::TransferCurrency {
+            player_id: "alice".to_string(),
+            recipient_id: recipient_id.to_string(),
+            recipient_chain,
+            amount,
+        };
+
+        // Admins credit and debit with checked arithmetic
+        let response = network.execute(chain1, &credit(100)).await.unwrap();
+        assert_eq!(response, RpgGameResponse::Balance { balance: 100 });
+        let response = network.execute(chain1, &credit(u64::MAX)).await;
+        assert!(refusal(response).contains(&RpgGameError::BalanceOverflow("alice".to_string()).to_string()));
+        let response = network.execute(chain1, &RpgGameOperation::DebitCurrency {
+            player_id: "alice".to_string(),
+            amount: 150,
+            reason: "fine".to_string(),
+        }).await;
+        assert!(refusal(response).contains(&RpgGameError::InsufficientFunds {
+            player_id: "alice".to_string(),
+            balance: 100,
+            amount: 150,
+        }.to_string()));
+
+        // Payment on the same chain
+        let response = network.execute(chain1, &transfer("bob", chain1, 30)).await.unwrap();
+        assert_eq!(response, RpgGameResponse::Balance { balance: 70 });
+        let response = network.execute(chain1, &transfer("bob", chain1, 71)).await;
+        assert!(refusal(response).contains("cannot pay 71"));
+
+        // Battles reported by the client pay nothing and cannot be replayed; admins reward each once
+        let battle = RpgGameOperation::RecordBattle {
+            battle_id: "battle1".to_string(),
+            player_id: "alice".to_string(),
+            opponent: "goblin".to_string(),
+            player_result: 2,
+            damage_dealt: 20,
+            damage_taken: 5,
+            experience_gained: 10,
+        };
+        network.execute(chain1, &battle).await.unwrap();
+        let response = network.execute(chain1, &battle).await;
+        assert!(refusal(response).contains(&RpgGameError::DuplicateBattle("battle1".to_string()).to_string()));
+        let reward_battle = |battle_id: &str| RpgGameOperation::RewardBattle {
+            battle_id: battle_id.to_string(),
+            amount: 25,
+        };
+        let response = network.execute(chain1, &reward_battle("battle1")).await.unwrap();
+        assert_eq!(response, RpgGameResponse::Balance { balance: 95 });
+        let response = network.execute(chain1, &reward_battle("battle1")).await;
+        assert!(refusal(response).contains(&RpgGameError::BattleAlreadyRewarded("battle1".to_string()).to_string()));
+        let response = network.execute(chain1, &reward_battle("battle2")).await;
+        assert!(refusal(response).contains(&RpgGameError::UnknownBattle("battle2".to_string()).to_string()));
+
+        // Only quests defined on chain pay, once, when an admin completes them; saves cannot claim them
+        network.execute(chain1, &RpgGameOperation::DefineQuest {
+            definition: rpg_game::QuestDefinition {
+                id: "quest1".to_string(),
+                title: "Goblin Hunt".to_string(),
+                reward: 50,
+            },
+        }).await.unwrap();
+        let save_quests = |completed| RpgGameOperation::SaveQuests {
+            player_id: "alice".to_string(),
+            quests: vec![quest("quest1", "Goblin Hunt", completed, 10), quest("made_up", "Free Gold", true, 1)],
+        };
+        let response = network.execute(chain1, &save_quests(true)).await;
+        assert!(refusal(response).contains(&RpgGameError::QuestCompletionMismatch {
+            quest_id: "quest1".to_string(),
+            completed: false,
+        }.to_string()));
+        network.execute(chain1, &save_quests(false)).await.unwrap();
+
+        let complete_quest = |quest_id: &str| RpgGameOperation::CompleteQuest {
+            player_id: "alice".to_string(),
+            quest_id: quest_id.to_string(),
+        };
+        let response = network.execute(chain1, &complete_quest("quest1")).await.unwrap();
+        assert_eq!(response, RpgGameResponse::Balance { balance: 145 });
+        let response = network.execute(chain1, &complete_quest("quest1")).await;
+        assert!(refusal(response).contains(&RpgGameError::QuestAlreadyCompleted {
+            player_id: "alice".to_string(),
+            quest_id: "quest1".to_string(),
+        }.to_string()));
+        let response = network.execute(chain1, &complete_quest("made_up")).await;
+        assert!(refusal(response).contains(&RpgGameError::UnknownQuest("made_up".to_string()).to_string()));
+        let response = network.execute(chain1, &save_quests(false)).await;
+        assert!(refusal(response).contains(&RpgGameError::QuestCompletionMismatch {
+            quest_id: "quest1".to_string(),
+            completed: true,
+        }.to_string()));
+        network.execute(chain1, &save_quests(true)).await.unwrap();
+
+        let state = network.view(chain1).await;
+        assert_eq!(state.balances.get(&"alice".to_string()).await.unwrap(), Some(145));
+        assert_eq!(state.balances.get(&"bob".to_string()).await.unwrap(), Some(30));
+
+        // Payment to another chain, then one the destination chain refuses
+        let response = network.execute(chain1, &transfer("carol", chain2, 45)).await.unwrap();
+        let RpgGameResponse::CurrencyTransferStarted { transfer_id, balance: 100 } = response else {
+            panic!("unexpected response {:?}", response);
+        };
+        network.process_inbox(chain2).await;
+        network.process_inbox(chain1).await;
+
+        let state = network.view(chain2).await;
+        assert_eq!(state.balances.get(&"carol".to_string()).await.unwrap(), Some(45));
+        let state = network.view(chain1).await;
+        let currency_transfer = state.currency_transfers.get(&transfer_id).await.unwrap().unwrap();
+        assert_eq!(currency_transfer.status, rpg_game::TransferStatus::Completed);
+
+        let response = network.execute(chain1, &transfer("dave", chain2, 10)).await.unwrap();
+        let RpgGameResponse::CurrencyTransferStarted { transfer_id, balance: 90 } = response else {
+            panic!("unexpected response {:?}", response);
+        };
+        network.process_inbox(chain2).await;
+        network.process_inbox(chain1).await;
+
+        let state = network.view(chain1).await;
+        let currency_transfer = state.currency_transfers.get(&transfer_id).await.unwrap().unwrap();
+        assert_eq!(currency_transfer.status, rpg_game::TransferStatus::Failed);
+        assert_eq!(state.balances.get(&"alice".to_string()).await.unwrap(), Some(100));
+
+        // The history accounts for every change
+        let history = state.currency_history.get(&"alice".to_string()).await.unwrap().unwrap();
+        let amounts = history.iter().map(|entry| entry.amount).collect::<Vec<_>>();
+        assert_eq!(amounts, vec![100, 30, 25, 50, 45, 10, 10]);
+        let balances = history.iter().map(|entry| entry.balance).collect::<Vec<_>>();
+        assert_eq!(balances, vec![100, 70, 95, 145, 100, 90, 100]);
+        let memos = history[2..4].iter().map(|entry| (entry.kind, entry.memo.as_str())).collect::<Vec<_>>();
+        assert_eq!(memos, [(LedgerEntryKind::BattleReward, "battle1"), (LedgerEntryKind::QuestReward, "quest1")]);
+    }
+
+    #[tokio::test]
+    async fn test_vendor_shops() {
+        let mut network = Network::default();
+
+        let chain1 = chain(0);
+
+        network.create_application(chain1, parameters("world1")).await;
+
+        for definition in [
+            rpg_game::ItemDefinition { stack_size: 5, ..item_definition("potion", None) },
+            item_definition("steel_sword", Some(rpg_game::EquipmentSlot::Weapon)),
+        ] {
+            network.execute(chain1, &RpgGameOperation::DefineItem { definition }).await.unwrap();
+        }
+
+        let potion = rpg_game::ShopListing {
+            item_id: "potion".to_string(),
+            price: 10,
+            buyback_price: Some(4),
+            max_stock: 6,
+            restock_quantity: 2,
+            restock_interval_micros: 60_000_000,
+        };
+        let sword = rpg_game::ShopListing {
+            item_id: "steel_sword".to_string(),
+            price: 100,
+            buyback_price: None,
+            max_stock: 1,
+            restock_quantity: 0,
+            restock_interval_micros: 0,
+        };
+        let define_shop = |listings| RpgGameOperation::DefineShop {
+            shop: rpg_game::ShopDefinition {
+                id: "apothecary".to_string(),
+                name: "Apothecary".to_string(),
+                listings,
+            },
+        };
+
+        // Listings must be known items that the shop does not buy back above their price
+        let response = network.execute(chain1, &define_shop(vec![rpg_game::ShopListing {
+            buyback_price: Some(11),
+            ..potion.clone()
+        }])).await;
+        assert!(refusal(response).contains("invalid shop"));
+        let response = network.execute(chain1, &define_shop(vec![rpg_game::ShopListing {
+            item_id: "elixir".to_string(),
+            ..potion.clone()
+        }])).await;
+        assert!(refusal(response).contains(&RpgGameError::UnknownItem("elixir".to_string()).to_string()));
+        network.execute(chain1, &define_shop(vec![potion, sword])).await.unwrap();
+
+        let player_id = "shopper".to_string();
+        network.execute(chain1, &RpgGameOperation::RegisterPlayer {
+            player_id: player_id.clone(),
+        }).await.unwrap();
+        let credit = |amount| RpgGameOperation::CreditCurrency {
+            player_id: player_id.clone(),
+            amount,
+            reason: "allowance".to_string(),
+        };
+        let buy = |item_id: &str, quantity| RpgGameOperation::BuyItem {
+            player_id: player_id.clone(),
+            shop_id: "apothecary".to_string(),
+            item_id: item_id.to_string(),
+            quantity,
+        };
+        let sell = |slot: &str, quantity| RpgGameOperation::SellItem {
+            player_id: player_id.clone(),
+            shop_id: "apothecary".to_string(),
+            slot: slot.to_string(),
+            quantity,
+        };
+        network.execute(chain1, &credit(50)).await.unwrap();
+
+        let response = network.execute(chain1, &buy("potion", 3)).await.unwrap();
+        assert_eq!(response, RpgGameResponse::ItemBought { slot: "inventory-1".to_string(), balance: 20 });
+        let response = network.execute(chain1, &buy("potion", 3)).await;
+        assert!(refusal(response).contains(&RpgGameError::InsufficientFunds {
+            player_id: player_id.clone(),
+            balance: 20,
+            amount: 30,
+        }.to_string()));
+
+        network.execute(chain1, &credit(100)).await.unwrap();
+        let response = network.execute(chain1, &buy("potion", 4)).await;
+        assert!(refusal(response).contains(&RpgGameError::OutOfStock {
+            shop_id: "apothecary".to_string(),
+            item_id: "potion".to_string(),
+            stock: 3,
+        }.to_string()));
+        let response = network.execute(chain1, &buy("steel_sword", 1)).await.unwrap();
+        assert_eq!(response, RpgGameResponse::ItemBought { slot: "inventory-2".to_string(), balance: 20 });
+
+        // The shop only buys back the items it has a buyback price for
+        let response = network.execute(chain1, &sell("inventory-2", 1)).await;
+        assert!(refusal(response).contains(&RpgGameError::NotInShop {
+            shop_id: "apothecary".to_string(),
+            item_id: "steel_sword".to_string(),
+        }.to_string()));
+        let response = network.execute(chain1, &sell("inventory-1", 2)).await.unwrap();
+        assert_eq!(response, RpgGameResponse::Balance { balance: 28 });
+
+        let state = network.view(chain1).await;
+        let inventory = state.player_inventories.get(&player_id).await.unwrap().unwrap();
+        assert_eq!(inventory.item_in_slot("inventory-1").unwrap().quantity, 1);
+        let mut shop = state.shops.get(&"apothecary".to_string()).await.unwrap().unwrap();
+        assert_eq!(shop.item_mut("steel_sword").unwrap().stock, 0);
+
+        // Restocks are due every interval and stop at the maximum stock
+        let item = shop.item_mut("potion").unwrap();
+        assert_eq!(item.stock, 5);
+        let opened_at = item.last_restock;
+        item.restock(opened_at + 59_000_000);
+        assert_eq!(item.stock, 5);
+        item.restock(opened_at + 120_000_000);
+        assert_eq!(item.stock, 6);
+        assert_eq!(item.last_restock, opened_at + 120_000_000);
+    }
+}
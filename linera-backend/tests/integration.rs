@@ -4,8 +4,7 @@
 //! with multiple chains and cross-chain communication.
 //! SPDX-License-Identifier: MIT
 
-use linera_sdk::{ContractAbi, ServiceAbi};
-use serde_json::Value;
+use linera_sdk::linera_base_types::{ContractAbi, ServiceAbi};
 
 #[cfg(not(target_arch = "wasm32"))]
 #[tokio::test]
@@ -46,11 +45,8 @@ async fn test_rpg_game_integration() -> Result<(), Box<dyn std::error::Error>> {
         _ => panic!("Deserialized to wrong operation type"),
     }
     
-    // Test GraphQL query serialization
-    let query = async_graphql::Request::new("query { __typename }");
-    let serialized_query = <RpgGameAbi as ServiceAbi>::serialize_query(&query)?;
-    let _deserialized_query: async_graphql::Request = 
-        <RpgGameAbi as ServiceAbi>::deserialize_query(serialized_query)?;
+    // Verify the service takes GraphQL requests
+    let _query: <RpgGameAbi as ServiceAbi>::Query = async_graphql::Request::new("query { __typename }");
     
     Ok(())
 }
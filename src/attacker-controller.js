@@ -68,7 +68,11 @@ export const attack_controller = (() => {
     
           let damage = this.GetComponent('HealthComponent')._params.strength;
           if (item) {
-            damage *= item.Params.damage;
+            // On chain, the item's damage comes from the catalogue rather than the client
+            const blockchainManager = this.GetComponent('BlockchainManager');
+            const params = blockchainManager && blockchainManager.isConnected ?
+                blockchainManager.itemParams(equip.Name) : item.Params;
+            damage *= (params && params.damage) || 1;
             damage = Math.round(damage);
           }
 
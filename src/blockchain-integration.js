@@ -6,6 +6,17 @@ import {entity} from './entity.js';
  * This module handles connection to Linera microchains for storing game state.
  */
 export const blockchain_integration = (() => {
+
+  // Stats of a new character on chain; see linera-backend/src/progression.rs
+  const STARTING_STATS = {
+    maxHealth: 100,
+    strength: 10,
+    wisdomness: 5,
+    benchpress: 5,
+    curl: 5,
+    experience: 0,
+    level: 1,
+  };
   
   class BlockchainManager extends entity.Component {
     constructor(params) {
@@ -14,6 +25,9 @@ export const blockchain_integration = (() => {
       this._client = null;
       this._application = null;
       this._isConnected = false;
+      this._owner = null;
+      this._registeredPlayers = new Set();
+      this._itemParams = new Map();
     }
 
     async InitComponent() {
@@ -37,6 +51,15 @@ export const blockchain_integration = (() => {
       }
     }
 
+    _showBlockchainError(message) {
+      console.error(message);
+      const statusElement = document.getElementById('blockchain-status-text');
+      if (statusElement) {
+        statusElement.textContent = message;
+        statusElement.style.color = 'red';
+      }
+    }
+
     async _initializeBlockchain() {
       // Check if Linera client is available in the browser environment
       if (typeof window !== 'undefined' && typeof window.linera !== 'undefined') {
@@ -56,8 +79,11 @@ export const blockchain_integration = (() => {
           // Create a client instance
           if (window.linera && typeof window.linera.client !== 'undefined') {
             this._application = await window.linera.client.application(appId);
+            // Account that signs this client's blocks, to tell our players from other accounts' ones
+            this._owner = this._params.owner || window.linera.client.owner || null;
             this._isConnected = true;
             console.log('Connected to Linera application:', appId);
+            await this._loadItemCatalogue();
           } else {
             console.warn('Linera client not available, running without blockchain integration');
           }
@@ -70,8 +96,88 @@ export const blockchain_integration = (() => {
       }
     }
 
+    /**
+     * Load the parameters of every item in the chain's catalogue.
+     * Items granted on chain carry these parameters, whatever the client holds locally.
+     */
+    async _loadItemCatalogue() {
+      try {
+        const query = `query { itemCatalogue { id, params } }`;
+        const parsedResponse = JSON.parse(await this._application.query(`{ "query": "${query}" }`));
+        if (parsedResponse.errors) {
+          console.error('GraphQL errors:', parsedResponse.errors);
+          return;
+        }
+        for (const definition of parsedResponse.data.itemCatalogue) {
+          this._itemParams.set(definition.id, definition.params);
+        }
+      } catch (error) {
+        console.error('Error loading item catalogue from blockchain:', error);
+      }
+    }
+
+    /**
+     * Parameters of an item in the chain's catalogue, or null if the chain does not know it
+     * @param {string} itemId - Catalogue ID of the item
+     */
+    itemParams(itemId) {
+      return this._itemParams.get(itemId) || null;
+    }
+
+    /**
+     * Register the player to the signing account, unless it is registered already.
+     * The chain refuses every save for a player it does not know, or that another account owns.
+     * @param {string} playerId - Unique identifier for the player
+     */
+    async registerPlayer(playerId) {
+      if (!this._isConnected || !this._application) {
+        console.warn('Not connected to blockchain, cannot register player');
+        return false;
+      }
+      if (this._registeredPlayers.has(playerId)) {
+        return true;
+      }
+
+      try {
+        const query = `query { playerOwner(playerId: "${playerId}") }`;
+        const parsedResponse = JSON.parse(await this._application.query(`{ "query": "${query}" }`));
+        if (parsedResponse.errors) {
+          console.error('GraphQL errors:', parsedResponse.errors);
+          return false;
+        }
+
+        const owner = parsedResponse.data.playerOwner;
+        if (owner) {
+          if (!this._owner) {
+            this._showBlockchainError(`Signing account unknown, cannot check the owner of player ${playerId}`);
+            return false;
+          }
+          if (owner.toLowerCase() !== this._owner.toLowerCase()) {
+            this._showBlockchainError(`Player ${playerId} is owned by another account`);
+            return false;
+          }
+        } else {
+          const mutation = `mutation RegisterPlayer($playerId: String!) {
+            registerPlayer(playerId: $playerId)
+          }`;
+          const response = await this._application.query(JSON.stringify({
+            query: mutation,
+            variables: { playerId },
+          }));
+          console.log('Player registered on blockchain:', response);
+        }
+        this._registeredPlayers.add(playerId);
+        return true;
+      } catch (error) {
+        console.error('Error registering player on blockchain:', error);
+        return false;
+      }
+    }
+
     /**
      * Save player state to the blockchain
+     * Experience, level and stats only change on chain, so only the health is taken from playerData
+     * and the rest is the chain's record, or the starting stats for a new character.
      * @param {string} playerId - Unique identifier for the player
      * @param {Object} playerData - Player state to save
      */
@@ -80,20 +186,26 @@ export const blockchain_integration = (() => {
         console.warn('Not connected to blockchain, cannot save player state');
         return false;
       }
+      if (!await this.registerPlayer(playerId)) {
+        return false;
+      }
 
       try {
+        const recorded = await this.loadPlayerState(playerId) || STARTING_STATS;
+        const health = Math.min(playerData.health, recorded.maxHealth);
+
         // Prepare the mutation to save player state
         const mutation = `mutation { 
           savePlayerState(
             playerId: "${playerId}",
-            health: ${playerData.health},
-            maxHealth: ${playerData.maxHealth},
-            strength: ${playerData.strength},
-            wisdomness: ${playerData.wisdomness},
-            benchpress: ${playerData.benchpress},
-            curl: ${playerData.curl},
-            experience: ${playerData.experience},
-            level: ${playerData.level}
+            health: ${health},
+            maxHealth: ${recorded.maxHealth},
+            strength: ${recorded.strength},
+            wisdomness: ${recorded.wisdomness},
+            benchpress: ${recorded.benchpress},
+            curl: ${recorded.curl},
+            experience: ${recorded.experience},
+            level: ${recorded.level}
           ) 
         }`;
 
@@ -155,18 +267,21 @@ export const blockchain_integration = (() => {
         console.warn('Not connected to blockchain, cannot save inventory');
         return false;
       }
+      if (!await this.registerPlayer(playerId)) {
+        return false;
+      }
 
       try {
-        // Convert inventory to a format suitable for GraphQL
-        const inventoryJson = JSON.stringify(inventory);
-        const mutation = `mutation { 
-          saveInventory(
-            playerId: "${playerId}",
-            inventory: ${JSON.stringify(inventoryJson)}
-          ) 
+        // Items are sent as InventoryItemInput objects; the chain only accepts a rearrangement
+        // of the items it holds, and keeps their catalogue params
+        const mutation = `mutation SaveInventory($playerId: String!, $items: [InventoryItemInput!]!) {
+          saveInventory(playerId: $playerId, items: $items)
         }`;
 
-        const response = await this._application.query(`{ "query": "${mutation}" }`);
+        const response = await this._application.query(JSON.stringify({
+          query: mutation,
+          variables: { playerId, items: inventory },
+        }));
         console.log('Inventory saved to blockchain:', response);
         return true;
       } catch (error) {
@@ -187,7 +302,7 @@ export const blockchain_integration = (() => {
 
       try {
         const query = `query { 
-          inventory(playerId: "${playerId}") 
+          inventory(playerId: "${playerId}") { items { slot, itemId, quantity, params } }
         }`;
 
         const response = await this._application.query(`{ "query": "${query}" }`);
@@ -196,7 +311,8 @@ export const blockchain_integration = (() => {
           console.error('GraphQL errors:', parsedResponse.errors);
           return null;
         }
-        const data = parsedResponse.data.inventory;
+        const inventory = parsedResponse.data.inventory;
+        const data = inventory ? inventory.items : null;
         console.log('Inventory loaded from blockchain:', data);
         return data;
       } catch (error) {
@@ -207,6 +323,7 @@ export const blockchain_integration = (() => {
 
     /**
      * Save quests to the blockchain
+     * Quests of the chain's catalogue are only completed on chain, so their completion is the chain's record.
      * @param {string} playerId - Unique identifier for the player
      * @param {Array} quests - Array of quest objects
      */
@@ -215,18 +332,34 @@ export const blockchain_integration = (() => {
         console.warn('Not connected to blockchain, cannot save quests');
         return false;
       }
+      if (!await this.registerPlayer(playerId)) {
+        return false;
+      }
 
       try {
-        // Convert quests to a format suitable for GraphQL
-        const questsJson = JSON.stringify(quests);
-        const mutation = `mutation { 
-          saveQuests(
-            playerId: "${playerId}",
-            quests: ${JSON.stringify(questsJson)}
-          ) 
+        const query = `query {
+          questCatalogue { id }
+          quests(playerId: "${playerId}") { id, completed }
+        }`;
+        const parsedResponse = JSON.parse(await this._application.query(JSON.stringify({ query })));
+        if (parsedResponse.errors) {
+          console.error('GraphQL errors:', parsedResponse.errors);
+          return false;
+        }
+        const catalogue = new Set(parsedResponse.data.questCatalogue.map((definition) => definition.id));
+        const completed = new Set(
+            (parsedResponse.data.quests || []).filter((quest) => quest.completed).map((quest) => quest.id));
+        const saved = quests.map((quest) => catalogue.has(quest.id) ?
+            {...quest, completed: completed.has(quest.id)} : quest);
+
+        const mutation = `mutation SaveQuests($playerId: String!, $quests: [QuestInput!]!) {
+          saveQuests(playerId: $playerId, quests: $quests)
         }`;
 
-        const response = await this._application.query(`{ "query": "${mutation}" }`);
+        const response = await this._application.query(JSON.stringify({
+          query: mutation,
+          variables: { playerId, quests: saved },
+        }));
         console.log('Quests saved to blockchain:', response);
         return true;
       } catch (error) {
@@ -247,7 +380,7 @@ export const blockchain_integration = (() => {
 
       try {
         const query = `query { 
-          quests(playerId: "${playerId}") 
+          quests(playerId: "${playerId}") { id, title, text, completed, progress }
         }`;
 
         const response = await this._application.query(`{ "query": "${query}" }`);